@@ -0,0 +1,84 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Time source for expiration decisions and metadata timestamps.
+pub trait Clock: Send + Sync + Debug {
+    fn now(&self) -> SystemTime;
+}
+
+/// System time (`SystemTime::now()`), default clock
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock which is moved forward manually, for tests.
+///
+/// Clones share the same time, so the test can keep a handle and advance
+/// the clock used by `FileCacheService`.
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl ManualClock {
+    pub fn new(now: SystemTime) -> ManualClock {
+        ManualClock {
+            now: Arc::new(Mutex::new(now))
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now += duration;
+    }
+
+    pub fn set(&self, time: SystemTime) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now = time;
+    }
+}
+
+impl Default for ManualClock {
+    /// Starts at current system time
+    fn default() -> Self {
+        ManualClock::new(SystemTime::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod manual_clock_tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::clock::{Clock, ManualClock};
+
+    #[test]
+    fn clones_should_share_time() {
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let other = clock.clone();
+
+        clock.advance(Duration::from_secs(10));
+
+        assert_eq!(other.now(), UNIX_EPOCH + Duration::from_secs(10));
+    }
+
+    #[test]
+    fn time_should_be_set() {
+        let clock = ManualClock::default();
+
+        clock.set(UNIX_EPOCH + Duration::from_secs(100));
+
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(100));
+    }
+}
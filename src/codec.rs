@@ -0,0 +1,227 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::DeserializeOwned;
+use serde::de::value::BytesDeserializer;
+
+use crate::error::FileCacheError;
+use crate::types::OperationResult;
+
+/// Serialization format for cache items.
+///
+/// Built-in codecs are selected with `CodecKind`. Other formats can be plugged in
+/// by implementing this trait and passing codec to `FileCacheService::store_with_codec`
+/// and `FileCacheService::get_with_codec`, such items are marked with `CodecKind::Custom`.
+pub trait Codec {
+    fn encode<T: Serialize + ?Sized>(&self, item: &T) -> OperationResult<Vec<u8>>;
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> OperationResult<T>;
+}
+
+/// JSON codec (`serde_json`), default one
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize + ?Sized>(&self, item: &T) -> OperationResult<Vec<u8>> {
+        Ok(serde_json::to_vec(item)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> OperationResult<T> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+/// Bincode codec, compact binary format. Requires `bincode` feature.
+#[cfg(feature = "bincode")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize + ?Sized>(&self, item: &T) -> OperationResult<Vec<u8>> {
+        bincode::serialize(item).map_err(|e| FileCacheError::CodecError(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> OperationResult<T> {
+        bincode::deserialize(data).map_err(|e| FileCacheError::CodecError(e.to_string()))
+    }
+}
+
+/// MessagePack codec. Requires `msgpack` feature.
+///
+/// Structs are encoded as maps (with field names), so fields can be added later.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePackCodec {
+    fn encode<T: Serialize + ?Sized>(&self, item: &T) -> OperationResult<Vec<u8>> {
+        rmp_serde::to_vec_named(item).map_err(|e| FileCacheError::CodecError(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> OperationResult<T> {
+        rmp_serde::from_slice(data).map_err(|e| FileCacheError::CodecError(e.to_string()))
+    }
+}
+
+/// CBOR codec. Requires `cbor` feature.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl Codec for CborCodec {
+    fn encode<T: Serialize + ?Sized>(&self, item: &T) -> OperationResult<Vec<u8>> {
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(item, &mut data)
+            .map_err(|e| FileCacheError::CodecError(e.to_string()))?;
+        Ok(data)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> OperationResult<T> {
+        ciborium::de::from_reader(data).map_err(|e| FileCacheError::CodecError(e.to_string()))
+    }
+}
+
+/// Codec selector for `FileCacheService`.
+///
+/// Stored in item metadata, so items written with different codecs
+/// can live side by side and are always decoded with the codec they were written with.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CodecKind {
+    #[default]
+    Json,
+
+    #[cfg(feature = "bincode")]
+    Bincode,
+
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+
+    #[cfg(feature = "cbor")]
+    Cbor,
+
+    /// Item has been stored with codec passed to `FileCacheService::store_with_codec`,
+    /// it can be read only with `FileCacheService::get_with_codec`. Can't be set for service or namespace.
+    Custom,
+}
+
+impl CodecKind {
+    /// All built-in codecs enabled with cargo features
+    pub fn all() -> &'static [CodecKind] {
+        &[
+            CodecKind::Json,
+            #[cfg(feature = "bincode")]
+            CodecKind::Bincode,
+            #[cfg(feature = "msgpack")]
+            CodecKind::MessagePack,
+            #[cfg(feature = "cbor")]
+            CodecKind::Cbor,
+        ]
+    }
+
+    /// Cache file extension, without dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CodecKind::Json => "json",
+            #[cfg(feature = "bincode")]
+            CodecKind::Bincode => "bin",
+            #[cfg(feature = "msgpack")]
+            CodecKind::MessagePack => "msgpack",
+            #[cfg(feature = "cbor")]
+            CodecKind::Cbor => "cbor",
+            // Items of custom codecs are always stored in entry files (`StorageFormat::V2`)
+            CodecKind::Custom => "custom",
+        }
+    }
+}
+
+impl Codec for CodecKind {
+    fn encode<T: Serialize + ?Sized>(&self, item: &T) -> OperationResult<Vec<u8>> {
+        match self {
+            CodecKind::Json => JsonCodec.encode(item),
+            #[cfg(feature = "bincode")]
+            CodecKind::Bincode => BincodeCodec.encode(item),
+            #[cfg(feature = "msgpack")]
+            CodecKind::MessagePack => MessagePackCodec.encode(item),
+            #[cfg(feature = "cbor")]
+            CodecKind::Cbor => CborCodec.encode(item),
+            CodecKind::Custom => Err(FileCacheError::CodecError(
+                "items of custom codec are stored with store_with_codec".to_string()))
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> OperationResult<T> {
+        match self {
+            CodecKind::Json => JsonCodec.decode(data),
+            #[cfg(feature = "bincode")]
+            CodecKind::Bincode => BincodeCodec.decode(data),
+            #[cfg(feature = "msgpack")]
+            CodecKind::MessagePack => MessagePackCodec.decode(data),
+            #[cfg(feature = "cbor")]
+            CodecKind::Cbor => CborCodec.decode(data),
+            // Payload is passed as is, see `CustomPayload`
+            CodecKind::Custom => T::deserialize(BytesDeserializer::<serde::de::value::Error>::new(data))
+                .map_err(|e| FileCacheError::CodecError(e.to_string())),
+        }
+    }
+}
+
+/// Payload of item stored with custom codec, decoded by caller of `get_with_codec`
+pub(crate) struct CustomPayload(pub(crate) Vec<u8>);
+
+impl<'de> Deserialize<'de> for CustomPayload {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PayloadVisitor;
+
+        impl serde::de::Visitor<'_> for PayloadVisitor {
+            type Value = CustomPayload;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("payload of custom codec")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, data: &[u8]) -> Result<CustomPayload, E> {
+                Ok(CustomPayload(data.to_vec()))
+            }
+        }
+
+        deserializer.deserialize_bytes(PayloadVisitor)
+    }
+}
+
+#[cfg(test)]
+mod codec_tests {
+    use crate::codec::{Codec, CodecKind};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn decoded_item_should_be_equal_to_original() {
+        for codec in CodecKind::all() {
+            let demo = get_demo_entity();
+
+            let data = codec.encode(&demo).unwrap();
+
+            assert_eq!(codec.decode::<Demo>(&data).unwrap(), demo);
+        }
+    }
+
+    #[test]
+    fn return_error_for_invalid_data() {
+        for codec in CodecKind::all() {
+            assert!(codec.decode::<Demo>(&[0xff, 0x00, 0x13]).is_err());
+        }
+    }
+
+    #[test]
+    fn extensions_should_be_unique() {
+        let codecs = CodecKind::all();
+
+        for (index, codec) in codecs.iter().enumerate() {
+            for other in &codecs[index + 1..] {
+                assert_ne!(codec.extension(), other.extension());
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::OperationResult;
+
+/// Compression of cache files payload.
+///
+/// Stored in item metadata, so items written with different compression
+/// settings can live side by side and are decompressed automatically.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+
+    /// Requires `zstd` feature
+    #[cfg(feature = "zstd")]
+    Zstd,
+
+    /// Requires `gzip` feature
+    #[cfg(feature = "gzip")]
+    Gzip,
+}
+
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
+
+impl Compression {
+    /// All compression algorithms enabled with cargo features
+    pub fn all() -> &'static [Compression] {
+        &[
+            Compression::None,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip,
+        ]
+    }
+
+    pub fn compress(&self, data: Vec<u8>) -> OperationResult<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data),
+
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(zstd::stream::encode_all(data.as_slice(), ZSTD_LEVEL)?),
+
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Write;
+
+                let mut encoder = flate2::write::GzEncoder::new(
+                    Vec::new(), flate2::Compression::default());
+                encoder.write_all(&data)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+
+    pub fn decompress(&self, data: Vec<u8>) -> OperationResult<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data),
+
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(zstd::stream::decode_all(data.as_slice())?),
+
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Read;
+
+                let mut decompressed = Vec::new();
+                flate2::read::GzDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod compression_tests {
+    use crate::compression::Compression;
+
+    #[test]
+    fn decompressed_data_should_be_equal_to_original() {
+        let data = "demo-data".repeat(100).into_bytes();
+
+        for compression in Compression::all() {
+            let compressed = compression.compress(data.clone()).unwrap();
+            assert_eq!(compression.decompress(compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn return_error_for_invalid_data() {
+        for compression in Compression::all().iter().filter(|c| **c != Compression::None) {
+            assert!(compression.decompress(vec![0x01, 0x02, 0x03]).is_err());
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::CodecKind;
+use crate::compression::Compression;
+use crate::corruption::CorruptionPolicy;
+use crate::error::FileCacheError;
+use crate::format::StorageFormat;
+use crate::key::encode_key;
+use crate::limits::CacheLimits;
+use crate::types::EmptyResult;
+
+/// When cache files are flushed to disk
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FsyncPolicy {
+    /// Files and directory entries are flushed (fsync) on each write, survive power loss
+    #[default]
+    Always,
+
+    /// Flushing is left to OS: faster writes, recent items could be lost on power loss.
+    /// Writes are still atomic for readers.
+    Never,
+}
+
+/// File cache service settings, see `FileCacheServiceBuilder::from_config`.
+///
+/// Can be deserialized from application config, only `root_path` and `instance_name` are required:
+///
+/// ```toml
+/// root_path = "/opt/myapp/cache"
+/// instance_name = "demo-instance"
+/// default_ttl_secs = 3600
+/// codec = "json"
+/// compression = "none"
+/// file_mode = 0o640
+/// fsync = "always"
+/// corruption_policy = "quarantine"
+/// quarantine_retention_secs = 604800
+/// lock_timeout_ms = 5000
+/// sliding_expiration = false
+/// grace_period_secs = 300
+/// cross_process_single_flight = false
+/// early_refresh_beta = 1.0
+/// storage_format = "v2"
+///
+/// [limits]
+/// max_total_bytes = 536870912
+///
+/// [memory_limits]
+/// max_entries = 10000
+///
+/// [namespace_limits.tokens]
+/// max_entries = 1000
+/// ```
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct FileCacheConfig {
+    /// Root path to cache directory (will be created if doesn't exist)
+    pub root_path: PathBuf,
+
+    /// Name of cache instance, included in file hierarchy
+    pub instance_name: String,
+
+    /// Expiry of items stored with `store_default`, `None` - never expire
+    #[serde(default)]
+    pub default_ttl_secs: Option<u64>,
+
+    /// Codec for new items
+    #[serde(default)]
+    pub codec: CodecKind,
+
+    /// Compression for new items
+    #[serde(default)]
+    pub compression: Compression,
+
+    /// Unix permissions of cache files (e.g. `0o640`), `None` - default permissions of new files (umask applies).
+    /// Ignored on other platforms.
+    #[serde(default)]
+    pub file_mode: Option<u32>,
+
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+
+    /// Instance limits
+    #[serde(default)]
+    pub limits: CacheLimits,
+
+    /// Namespace limits, key - namespace
+    #[serde(default)]
+    pub namespace_limits: HashMap<String, CacheLimits>,
+
+    #[serde(default)]
+    pub corruption_policy: CorruptionPolicy,
+
+    /// Quarantined items older than this are removed by `FileCacheService::purge_expired`,
+    /// `None` - kept until removed manually
+    #[serde(default)]
+    pub quarantine_retention_secs: Option<u64>,
+
+    /// Max time to wait for namespace lock in milliseconds, `None` - wait forever
+    #[serde(default)]
+    pub lock_timeout_ms: Option<u64>,
+
+    /// Push expiration of item forward on each successful `get`, see `FileCacheServiceBuilder::sliding_expiration`
+    #[serde(default)]
+    pub sliding_expiration: bool,
+
+    /// Time after expiration while item can be read as stale, see `FileCacheService::get_allow_stale`
+    #[serde(default)]
+    pub grace_period_secs: Option<u64>,
+
+    /// Single-flight of `get_or_insert_with` across processes, see `FileCacheServiceBuilder::cross_process_single_flight`
+    #[serde(default)]
+    pub cross_process_single_flight: bool,
+
+    /// Early refresh of items in `get_or_insert_with`, see `FileCacheServiceBuilder::early_refresh`
+    #[serde(default)]
+    pub early_refresh_beta: Option<f64>,
+
+    /// In-memory tier limits, see `FileCacheServiceBuilder::memory_tier`. `None` - disabled
+    #[serde(default)]
+    pub memory_limits: Option<CacheLimits>,
+
+    /// Layout of new items on disk, see `FileCacheServiceBuilder::storage_format`
+    #[serde(default)]
+    pub storage_format: StorageFormat,
+}
+
+impl FileCacheConfig {
+    pub fn new(root_path: impl Into<PathBuf>, instance_name: &str) -> FileCacheConfig {
+        FileCacheConfig {
+            root_path: root_path.into(),
+            instance_name: instance_name.to_string(),
+            default_ttl_secs: None,
+            codec: CodecKind::default(),
+            compression: Compression::default(),
+            file_mode: None,
+            fsync: FsyncPolicy::default(),
+            limits: CacheLimits::default(),
+            namespace_limits: HashMap::new(),
+            corruption_policy: CorruptionPolicy::default(),
+            quarantine_retention_secs: None,
+            lock_timeout_ms: None,
+            sliding_expiration: false,
+            grace_period_secs: None,
+            cross_process_single_flight: false,
+            early_refresh_beta: None,
+            memory_limits: None,
+            storage_format: StorageFormat::default(),
+        }
+    }
+
+    /// Returns `FileCacheError::InvalidConfig` for settings which can't work
+    pub fn validate(&self) -> EmptyResult {
+        if self.root_path.as_os_str().is_empty() {
+            return Err(invalid_config("root_path is empty"))
+        }
+
+        if self.instance_name.trim().is_empty() {
+            return Err(invalid_config("instance_name is blank"))
+        }
+
+        encode_key(&self.instance_name)?;
+
+        if self.default_ttl_secs == Some(0) {
+            return Err(invalid_config("default_ttl_secs is 0, items would expire right away"))
+        }
+
+        if self.codec == CodecKind::Custom {
+            return Err(invalid_config("codec can't be custom, custom codec is passed to store_with_codec"))
+        }
+
+        if let Some(file_mode) = self.file_mode {
+            if file_mode > 0o7777 {
+                return Err(invalid_config(&format!("file_mode {:o} is invalid", file_mode)))
+            }
+
+            if file_mode & 0o600 != 0o600 {
+                return Err(invalid_config(&format!("file_mode {:o} doesn't allow owner to read and write", file_mode)))
+            }
+        }
+
+        if let Some(beta) = self.early_refresh_beta {
+            if !beta.is_finite() || beta <= 0.0 {
+                return Err(invalid_config(&format!("early_refresh_beta {} must be positive", beta)))
+            }
+        }
+
+        validate_limits("limits", &self.limits)?;
+
+        if let Some(memory_limits) = &self.memory_limits {
+            if memory_limits.is_unlimited() {
+                return Err(invalid_config("memory_limits must set max_total_bytes or max_entries"))
+            }
+
+            validate_limits("memory_limits", memory_limits)?;
+        }
+
+        for (namespace, limits) in &self.namespace_limits {
+            if namespace.trim().is_empty() {
+                return Err(invalid_config("namespace_limits contains blank namespace"))
+            }
+
+            encode_key(namespace)?;
+            validate_limits(&format!("namespace_limits.{}", namespace), limits)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn validate_limits(name: &str, limits: &CacheLimits) -> EmptyResult {
+    if limits.max_total_bytes == Some(0) || limits.max_entries == Some(0) {
+        return Err(invalid_config(&format!("{} can't be 0, omit limit for unlimited cache", name)))
+    }
+
+    Ok(())
+}
+
+pub(crate) fn invalid_config(message: &str) -> FileCacheError {
+    FileCacheError::InvalidConfig(message.to_string())
+}
+
+#[cfg(test)]
+mod file_cache_config_tests {
+    use std::path::PathBuf;
+
+    use crate::config::{FileCacheConfig, FsyncPolicy};
+    use crate::corruption::CorruptionPolicy;
+    use crate::error::FileCacheError;
+    use crate::format::StorageFormat;
+    use crate::limits::CacheLimits;
+
+    #[test]
+    fn only_root_path_and_instance_name_should_be_required() {
+        let config: FileCacheConfig = serde_json::from_str(
+            r#"{"root_path": "/tmp/cache", "instance_name": "demo"}"#).unwrap();
+
+        assert_eq!(config, FileCacheConfig::new("/tmp/cache", "demo"));
+    }
+
+    #[test]
+    fn all_settings_should_be_deserialized() {
+        let config: FileCacheConfig = serde_json::from_str(r#"{
+            "root_path": "/tmp/cache",
+            "instance_name": "demo",
+            "default_ttl_secs": 60,
+            "codec": "json",
+            "compression": "none",
+            "file_mode": 416,
+            "fsync": "never",
+            "limits": {"max_total_bytes": 1024},
+            "namespace_limits": {"tokens": {"max_entries": 10}},
+            "corruption_policy": "quarantine",
+            "quarantine_retention_secs": 86400,
+            "lock_timeout_ms": 500,
+            "sliding_expiration": true,
+            "grace_period_secs": 300,
+            "cross_process_single_flight": true,
+            "early_refresh_beta": 1.5,
+            "memory_limits": {"max_entries": 100},
+            "storage_format": "v2"
+        }"#).unwrap();
+
+        assert_eq!(config.root_path, PathBuf::from("/tmp/cache"));
+        assert_eq!(config.default_ttl_secs, Some(60));
+        assert_eq!(config.file_mode, Some(0o640));
+        assert_eq!(config.fsync, FsyncPolicy::Never);
+        assert_eq!(config.limits.max_total_bytes, Some(1024));
+        assert_eq!(config.namespace_limits.get("tokens").unwrap().max_entries, Some(10));
+        assert_eq!(config.corruption_policy, CorruptionPolicy::Quarantine);
+        assert_eq!(config.quarantine_retention_secs, Some(86400));
+        assert_eq!(config.lock_timeout_ms, Some(500));
+        assert!(config.sliding_expiration);
+        assert_eq!(config.grace_period_secs, Some(300));
+        assert!(config.cross_process_single_flight);
+        assert_eq!(config.early_refresh_beta, Some(1.5));
+        assert_eq!(config.memory_limits.unwrap().max_entries, Some(100));
+        assert_eq!(config.storage_format, StorageFormat::V2);
+
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn invalid_settings_should_be_rejected() {
+        let mut config = FileCacheConfig::new("/tmp/cache", " ");
+        assert!(matches!(config.validate(), Err(FileCacheError::InvalidConfig(_))));
+
+        config = FileCacheConfig::new("/tmp/cache", "demo");
+        config.default_ttl_secs = Some(0);
+        assert!(matches!(config.validate(), Err(FileCacheError::InvalidConfig(_))));
+
+        config = FileCacheConfig::new("/tmp/cache", "demo");
+        config.file_mode = Some(0o444);
+        assert!(matches!(config.validate(), Err(FileCacheError::InvalidConfig(_))));
+
+        config = FileCacheConfig::new("/tmp/cache", "demo");
+        config.namespace_limits.insert("tokens".to_string(), CacheLimits { max_total_bytes: None, max_entries: Some(0) });
+        assert!(matches!(config.validate(), Err(FileCacheError::InvalidConfig(_))));
+
+        config = FileCacheConfig::new("/tmp/cache", "demo");
+        config.early_refresh_beta = Some(0.0);
+        assert!(matches!(config.validate(), Err(FileCacheError::InvalidConfig(_))));
+
+        config = FileCacheConfig::new("/tmp/cache", "demo");
+        config.memory_limits = Some(CacheLimits::default());
+        assert!(matches!(config.validate(), Err(FileCacheError::InvalidConfig(_))));
+    }
+}
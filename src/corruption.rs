@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// What to do with corrupted items: metadata or cache file can't be read,
+/// or cache file doesn't match requested type.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CorruptionPolicy {
+    /// Remove item files
+    #[default]
+    Delete,
+
+    /// Move item files into `[INSTANCE NAME]/.quarantine/[NAMESPACE]/[ITEM-NAME]-[UNIXTIME]/`
+    /// with `reason.txt` file, see `FileCacheServiceBuilder::quarantine_retention`
+    Quarantine,
+
+    /// Keep item files, `get` returns `FileCacheError::Corrupted` or `FileCacheError::SchemaMismatch`
+    Error,
+}
@@ -0,0 +1,99 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::codec::CodecKind;
+use crate::compression::Compression;
+use crate::service::FileCacheItemMetadata;
+
+/// Cache entry details, see `FileCacheService::metadata` and `FileCacheService::get_with_metadata`.
+///
+/// Times have seconds precision, as stored in metadata.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CacheEntryInfo {
+    pub created: SystemTime,
+
+    /// Expiration time, `None` - never expires
+    pub expires_at: Option<SystemTime>,
+
+    /// Time to live from creation, `None` - never expires
+    pub ttl: Option<Duration>,
+
+    /// Time left until expiration, `None` - never expires.
+    /// Can be used for HTTP `Cache-Control: max-age`.
+    pub remaining_ttl: Option<Duration>,
+
+    /// Cache file size in bytes (after compression)
+    pub payload_size: u64,
+
+    pub codec: CodecKind,
+
+    pub compression: Compression,
+}
+
+impl CacheEntryInfo {
+    pub(crate) fn new(metadata: &FileCacheItemMetadata, payload_size: u64, now_unixtime: u64) -> CacheEntryInfo {
+        let expires_at_unixtime = metadata.get_expires_at_unixtime();
+
+        CacheEntryInfo {
+            created: UNIX_EPOCH + Duration::from_secs(metadata.created_unixtime),
+            expires_at: expires_at_unixtime.map(|expires_at| UNIX_EPOCH + Duration::from_secs(expires_at)),
+            ttl: expires_at_unixtime.map(|expires_at|
+                Duration::from_secs(expires_at.saturating_sub(metadata.created_unixtime))),
+            remaining_ttl: expires_at_unixtime.map(|expires_at|
+                Duration::from_secs(expires_at.saturating_sub(now_unixtime))),
+            payload_size,
+            codec: metadata.codec,
+            compression: metadata.compression,
+        }
+    }
+}
+
+#[cfg(test)]
+mod cache_entry_info_tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::codec::CodecKind;
+    use crate::compression::Compression;
+    use crate::entry::CacheEntryInfo;
+    use crate::service::FileCacheItemMetadata;
+
+    fn get_metadata(ttl_secs: u64, expires_at: Option<u64>) -> FileCacheItemMetadata {
+        FileCacheItemMetadata {
+            ttl_secs,
+            created_unixtime: 1000,
+            expires_at,
+            relative_expiry: false,
+            codec: CodecKind::Json,
+            compression: Compression::None,
+            last_access_unixtime: None,
+            grace_period_secs: None,
+            compute_time_ms: None,
+        }
+    }
+
+    #[test]
+    fn remaining_ttl_should_be_calculated() {
+        let info = CacheEntryInfo::new(&get_metadata(60, Some(1060)), 128, 1045);
+
+        assert_eq!(info.created, UNIX_EPOCH + Duration::from_secs(1000));
+        assert_eq!(info.expires_at, Some(UNIX_EPOCH + Duration::from_secs(1060)));
+        assert_eq!(info.ttl, Some(Duration::from_secs(60)));
+        assert_eq!(info.remaining_ttl, Some(Duration::from_secs(15)));
+        assert_eq!(info.payload_size, 128);
+    }
+
+    #[test]
+    fn ttl_should_be_none_for_immortal_entry() {
+        let info = CacheEntryInfo::new(&get_metadata(0, None), 128, 5000);
+
+        assert!(info.expires_at.is_none());
+        assert!(info.ttl.is_none());
+        assert!(info.remaining_ttl.is_none());
+    }
+
+    #[test]
+    fn legacy_ttl_secs_should_be_used_without_expires_at() {
+        let info = CacheEntryInfo::new(&get_metadata(60, None), 128, 1010);
+
+        assert_eq!(info.remaining_ttl, Some(Duration::from_secs(50)));
+    }
+}
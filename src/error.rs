@@ -1,14 +1,62 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTimeError};
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum FileCacheError {
-    #[error("File cache error")]
-    Default,
+    /// Namespace, item or instance name can't be used as cache key
+    #[error("Invalid cache key: {0}")]
+    InvalidKey(String),
+
+    /// Service settings can't be used, see `FileCacheConfig::validate`
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+
+    /// Cache or metadata file can't be read: invalid format, broken compression, etc.
+    #[error("Corrupted cache file '{}': {reason}", .path.display())]
+    Corrupted {
+        path: PathBuf,
+        reason: String,
+    },
+
+    /// Cache file is valid, but its content doesn't match requested type
+    #[error("Cache file '{}' doesn't match requested type: {reason}", .path.display())]
+    SchemaMismatch {
+        path: PathBuf,
+        reason: String,
+    },
+
+    /// Clock returned time before unix epoch
+    #[error("Clock error: {0}")]
+    ClockError(#[from] SystemTimeError),
+
+    /// Namespace lock wasn't acquired within lock timeout
+    #[error("Lock '{}' wasn't acquired within {timeout:?}", .path.display())]
+    LockTimeout {
+        path: PathBuf,
+        timeout: Duration,
+    },
 
     #[error(transparent)]
     EncodingError(#[from] serde_json::Error),
 
+    /// Encoding or decoding error of non-JSON codec
+    #[error("Codec error: {0}")]
+    CodecError(String),
+
     /// Represents all other cases of `std::io::Error`.
     #[error(transparent)]
     IOError(#[from] std::io::Error),
-}
\ No newline at end of file
+}
+
+/// Error of `FileCacheService::get_or_try_insert_with`
+#[derive(Error, Debug)]
+pub enum GetOrInsertError<E> {
+    #[error(transparent)]
+    Cache(#[from] FileCacheError),
+
+    /// Value init function error
+    #[error("Value init error: {0}")]
+    Init(E),
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Cache item expiration.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Expiry {
+    /// Item never expires
+    Never,
+
+    /// Item expires after duration since store, rounded up to seconds.
+    /// Expiration has seconds precision: item is expired from expiration second on,
+    /// so `Duration::ZERO` means item is expired right away, it's not "immortal".
+    After(Duration),
+
+    /// Item expires at given time
+    At(SystemTime),
+}
+
+impl Expiry {
+    /// Expiration unix time (seconds) for item stored at `now_unixtime`, `None` - never
+    pub fn get_expires_at_unixtime(&self, now_unixtime: u64) -> Option<u64> {
+        match self {
+            Expiry::Never => None,
+            Expiry::After(duration) => {
+                let mut secs = duration.as_secs();
+
+                if duration.subsec_nanos() > 0 {
+                    secs += 1;
+                }
+
+                Some(now_unixtime.saturating_add(secs))
+            }
+            Expiry::At(time) => {
+                let unixtime = time.duration_since(UNIX_EPOCH)
+                                        .map(|duration| duration.as_secs()).unwrap_or(0);
+                Some(unixtime)
+            }
+        }
+    }
+}
+
+impl From<Duration> for Expiry {
+    fn from(duration: Duration) -> Self {
+        Expiry::After(duration)
+    }
+}
+
+impl From<SystemTime> for Expiry {
+    fn from(time: SystemTime) -> Self {
+        Expiry::At(time)
+    }
+}
+
+#[cfg(test)]
+mod expiry_tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::expiry::Expiry;
+
+    #[test]
+    fn never_should_not_have_expiration_time() {
+        assert!(Expiry::Never.get_expires_at_unixtime(1000).is_none());
+    }
+
+    #[test]
+    fn after_should_be_relative_to_now() {
+        let expiry = Expiry::After(Duration::from_secs(60));
+        assert_eq!(expiry.get_expires_at_unixtime(1000), Some(1060));
+    }
+
+    #[test]
+    fn zero_duration_should_expire_right_away() {
+        let expiry = Expiry::After(Duration::ZERO);
+        assert_eq!(expiry.get_expires_at_unixtime(1000), Some(1000));
+    }
+
+    #[test]
+    fn sub_second_duration_should_be_rounded_up() {
+        let expiry = Expiry::After(Duration::from_millis(1500));
+        assert_eq!(expiry.get_expires_at_unixtime(1000), Some(1002));
+    }
+
+    #[test]
+    fn at_should_be_absolute() {
+        let expiry = Expiry::At(UNIX_EPOCH + Duration::from_secs(5000));
+        assert_eq!(expiry.get_expires_at_unixtime(1000), Some(5000));
+    }
+}
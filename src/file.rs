@@ -0,0 +1,341 @@
+use std::fs::File;
+#[cfg(unix)]
+use std::fs::Permissions;
+use std::io::{ErrorKind, Write};
+#[cfg(feature = "tokio")]
+use std::path::PathBuf;
+use std::path::Path;
+
+use log::debug;
+use tempfile::NamedTempFile;
+
+use crate::error::FileCacheError;
+use crate::types::{EmptyResult, OperationResult};
+
+pub const TEMP_FILENAME_PREFIX: &str = ".tmp-";
+
+/// Mode of new files before umask is applied, same as `fs::write`
+#[cfg(unix)]
+const DEFAULT_FILE_MODE: u32 = 0o666;
+
+/// Cache files write settings
+#[derive(Clone, Copy, Debug)]
+pub struct WriteOptions {
+    /// Flush files and directory entries to disk (fsync)
+    pub fsync: bool,
+
+    /// Unix permissions of written files, `None` - default permissions of new files (umask applies)
+    pub file_mode: Option<u32>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions { fsync: true, file_mode: None }
+    }
+}
+
+/// Write `data` into temporary file in `dir`.
+///
+/// File content is flushed to disk (fsync) before return if enabled, so the file
+/// can be moved to its final destination with `persist_temp_file`.
+pub fn write_temp_file(dir: &Path, data: &[u8], options: &WriteOptions) -> OperationResult<NamedTempFile> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(TEMP_FILENAME_PREFIX);
+
+    // `tempfile` creates owner only files, cache files get usual permissions instead
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(DEFAULT_FILE_MODE));
+
+    let mut temp_file = builder.tempfile_in(dir)?;
+
+    if let Some(file_mode) = options.file_mode {
+        set_file_mode(temp_file.as_file(), file_mode)?;
+    }
+
+    temp_file.write_all(data)?;
+
+    if options.fsync {
+        temp_file.as_file().sync_all()?;
+    }
+
+    debug!("temp file '{}' has been written", temp_file.path().display());
+
+    Ok(temp_file)
+}
+
+/// Move temporary file to `file_path` with atomic rename.
+///
+/// Readers see either previous file content or the new one, never a partial write.
+pub fn persist_temp_file(temp_file: NamedTempFile, file_path: &Path) -> EmptyResult {
+    temp_file.persist(file_path).map_err(|e| e.error)?;
+    debug!("file '{}' has been persisted", file_path.display());
+    Ok(())
+}
+
+/// Write `data` into `file_path` with temp file + fsync + rename.
+pub fn write_file_atomically(file_path: &Path, data: &[u8], options: &WriteOptions) -> EmptyResult {
+    let dir = match file_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new(".")
+    };
+
+    let temp_file = write_temp_file(dir, data, options)?;
+    persist_temp_file(temp_file, file_path)?;
+
+    if options.fsync {
+        sync_dir(dir)?;
+    }
+
+    Ok(())
+}
+
+/// Async version of `write_temp_file`, returns path of temporary file in `dir`
+#[cfg(feature = "tokio")]
+pub async fn write_temp_file_async(dir: &Path, data: &[u8], options: &WriteOptions) -> OperationResult<PathBuf> {
+    use tokio::io::AsyncWriteExt;
+
+    let temp_file_path = dir.join(get_temp_filename());
+
+    let mut open_options = tokio::fs::OpenOptions::new();
+    open_options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    open_options.mode(DEFAULT_FILE_MODE);
+
+    let result = async {
+        let mut temp_file = open_options.open(&temp_file_path).await?;
+
+        if let Some(file_mode) = options.file_mode {
+            set_file_mode_async(&temp_file_path, file_mode).await?;
+        }
+
+        temp_file.write_all(data).await?;
+
+        if options.fsync {
+            temp_file.sync_all().await?;
+        }
+
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(()) => {
+            debug!("temp file '{}' has been written", temp_file_path.display());
+            Ok(temp_file_path)
+        }
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&temp_file_path).await;
+            Err(e)
+        }
+    }
+}
+
+/// Async version of `persist_temp_file`
+#[cfg(feature = "tokio")]
+pub async fn persist_temp_file_async(temp_file_path: &Path, file_path: &Path) -> EmptyResult {
+    if let Err(e) = tokio::fs::rename(temp_file_path, file_path).await {
+        let _ = tokio::fs::remove_file(temp_file_path).await;
+        return Err(FileCacheError::from(e))
+    }
+
+    debug!("file '{}' has been persisted", file_path.display());
+    Ok(())
+}
+
+/// Async version of `write_file_atomically`
+#[cfg(feature = "tokio")]
+pub async fn write_file_atomically_async(file_path: &Path, data: &[u8], options: &WriteOptions) -> EmptyResult {
+    let dir = match file_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new(".")
+    };
+
+    let temp_file_path = write_temp_file_async(dir, data, options).await?;
+    persist_temp_file_async(&temp_file_path, file_path).await?;
+
+    if options.fsync {
+        sync_dir_async(dir).await?;
+    }
+
+    Ok(())
+}
+
+/// Async version of `sync_dir`
+#[cfg(all(feature = "tokio", unix))]
+pub async fn sync_dir_async(dir: &Path) -> EmptyResult {
+    tokio::fs::File::open(dir).await?.sync_all().await?;
+    Ok(())
+}
+
+#[cfg(all(feature = "tokio", not(unix)))]
+pub async fn sync_dir_async(_dir: &Path) -> EmptyResult {
+    Ok(())
+}
+
+/// Unique temporary file name, `tempfile` naming isn't available for async writes
+#[cfg(feature = "tokio")]
+fn get_temp_filename() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0);
+
+    format!("{}{}-{}-{}", TEMP_FILENAME_PREFIX, std::process::id(), nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// File size in bytes, `0` if file doesn't exist
+pub fn get_file_size(file_path: &Path) -> OperationResult<u64> {
+    match std::fs::metadata(file_path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(FileCacheError::from(e))
+    }
+}
+
+/// Async version of `get_file_size`
+#[cfg(feature = "tokio")]
+pub async fn get_file_size_async(file_path: &Path) -> OperationResult<u64> {
+    match tokio::fs::metadata(file_path).await {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(FileCacheError::from(e))
+    }
+}
+
+/// Flush directory entries (renames) to disk.
+#[cfg(unix)]
+pub fn sync_dir(dir: &Path) -> EmptyResult {
+    std::fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn sync_dir(_dir: &Path) -> EmptyResult {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_file_mode(file: &File, file_mode: u32) -> EmptyResult {
+    use std::os::unix::fs::PermissionsExt;
+
+    file.set_permissions(Permissions::from_mode(file_mode))?;
+    Ok(())
+}
+
+/// File permissions are supported on unix only
+#[cfg(not(unix))]
+fn set_file_mode(_file: &File, _file_mode: u32) -> EmptyResult {
+    Ok(())
+}
+
+#[cfg(all(feature = "tokio", unix))]
+async fn set_file_mode_async(file_path: &Path, file_mode: u32) -> EmptyResult {
+    use std::os::unix::fs::PermissionsExt;
+
+    tokio::fs::set_permissions(file_path, Permissions::from_mode(file_mode)).await?;
+    Ok(())
+}
+
+#[cfg(all(feature = "tokio", not(unix)))]
+async fn set_file_mode_async(_file_path: &Path, _file_mode: u32) -> EmptyResult {
+    Ok(())
+}
+
+#[cfg(test)]
+mod persist_temp_file_tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::file::{persist_temp_file, TEMP_FILENAME_PREFIX, write_file_atomically, write_temp_file, WriteOptions};
+
+    #[test]
+    fn file_content_should_be_replaced() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("demo.json");
+
+        let first = write_temp_file(dir.path(), b"first", &WriteOptions::default()).unwrap();
+        persist_temp_file(first, &file_path).unwrap();
+
+        let second = write_temp_file(dir.path(), b"second", &WriteOptions::default()).unwrap();
+        persist_temp_file(second, &file_path).unwrap();
+
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "second");
+    }
+
+    #[test]
+    fn file_should_be_written_atomically() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("demo.json");
+
+        write_file_atomically(&file_path, b"first", &WriteOptions::default()).unwrap();
+        write_file_atomically(&file_path, b"second", &WriteOptions::default()).unwrap();
+
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "second");
+    }
+
+    #[test]
+    fn no_temp_files_should_be_left() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("demo.json");
+
+        let temp_file = write_temp_file(dir.path(), b"data", &WriteOptions::default()).unwrap();
+        persist_temp_file(temp_file, &file_path).unwrap();
+
+        let temp_files = fs::read_dir(dir.path()).unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(TEMP_FILENAME_PREFIX))
+            .count();
+
+        assert_eq!(temp_files, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_mode_should_be_applied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("demo.json");
+
+        let options = WriteOptions { fsync: false, file_mode: Some(0o640) };
+
+        write_file_atomically(&file_path, b"data", &options).unwrap();
+
+        assert_eq!(fs::metadata(&file_path).unwrap().permissions().mode() & 0o777, 0o640);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn default_file_mode_should_follow_umask() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("demo.json");
+        let plain_file_path = dir.path().join("plain.json");
+
+        write_file_atomically(&file_path, b"data", &WriteOptions::default()).unwrap();
+        fs::write(&plain_file_path, b"data").unwrap();
+
+        assert_eq!(fs::metadata(&file_path).unwrap().permissions().mode(),
+                   fs::metadata(&plain_file_path).unwrap().permissions().mode());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn file_should_be_written_atomically_async() {
+        use crate::file::write_file_atomically_async;
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("demo.json");
+
+        write_file_atomically_async(&file_path, b"first", &WriteOptions::default()).await.unwrap();
+        write_file_atomically_async(&file_path, b"second", &WriteOptions::default()).await.unwrap();
+
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "second");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use log::debug;
+
+/// In-process single-flight: at most one thread is in flight for the same key,
+/// others block in `enter` until the flight is over.
+///
+/// Clones share flights.
+#[derive(Clone, Default)]
+pub struct SingleFlight {
+    flights: Arc<Mutex<HashMap<String, Arc<Flight>>>>,
+}
+
+#[derive(Default)]
+struct Flight {
+    in_flight: Mutex<bool>,
+    landed: Condvar,
+}
+
+impl SingleFlight {
+    /// Enter flight for `key`, blocks while another thread is in flight for the same key
+    pub fn enter(&self, key: String) -> FlightGuard<'_> {
+        let flight = self.lock_flights().entry(key.clone()).or_default().clone();
+
+        {
+            let mut in_flight = lock(&flight.in_flight);
+
+            if *in_flight {
+                debug!("wait for flight '{}'", key);
+            }
+
+            while *in_flight {
+                in_flight = flight.landed.wait(in_flight).unwrap_or_else(PoisonError::into_inner);
+            }
+
+            *in_flight = true;
+        }
+
+        FlightGuard { single_flight: self, key, flight }
+    }
+
+    fn lock_flights(&self) -> MutexGuard<'_, HashMap<String, Arc<Flight>>> {
+        lock(&self.flights)
+    }
+}
+
+/// Flight is over on drop, one of waiting threads enters it
+pub struct FlightGuard<'a> {
+    single_flight: &'a SingleFlight,
+    key: String,
+    flight: Arc<Flight>,
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut flights = self.single_flight.lock_flights();
+
+        *lock(&self.flight.in_flight) = false;
+        self.flight.landed.notify_one();
+
+        // Only flight map and this guard hold the flight: nobody is waiting
+        if Arc::strong_count(&self.flight) == 2 {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+/// Async version of `SingleFlight`: at most one task is in flight for the same key,
+/// others wait in `enter` without blocking async runtime.
+///
+/// Clones share flights.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Default)]
+pub struct AsyncSingleFlight {
+    flights: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncSingleFlight {
+    /// Enter flight for `key`, waits while another task is in flight for the same key
+    pub async fn enter(&self, key: String) -> AsyncFlightGuard<'_> {
+        let flight = self.lock_flights().entry(key.clone()).or_default().clone();
+
+        let landed = match flight.clone().try_lock_owned() {
+            Ok(landed) => landed,
+            Err(_) => {
+                debug!("wait for flight '{}'", key);
+                flight.lock_owned().await
+            }
+        };
+
+        AsyncFlightGuard { single_flight: self, key, landed }
+    }
+
+    fn lock_flights(&self) -> MutexGuard<'_, HashMap<String, Arc<tokio::sync::Mutex<()>>>> {
+        lock(&self.flights)
+    }
+}
+
+/// Flight is over on drop, one of waiting tasks enters it
+#[cfg(feature = "tokio")]
+pub struct AsyncFlightGuard<'a> {
+    single_flight: &'a AsyncSingleFlight,
+    key: String,
+    landed: tokio::sync::OwnedMutexGuard<()>,
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for AsyncFlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut flights = self.single_flight.lock_flights();
+
+        // Only flight map and this guard hold the flight: nobody is waiting
+        if Arc::strong_count(tokio::sync::OwnedMutexGuard::mutex(&self.landed)) == 2 {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+/// Guarded data stays consistent even if thread has panicked in flight
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod single_flight_tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[cfg(feature = "tokio")]
+    use crate::flight::AsyncSingleFlight;
+    use crate::flight::SingleFlight;
+
+    #[test]
+    fn one_thread_should_be_in_flight_for_key() {
+        let single_flight = SingleFlight::default();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _flight = single_flight.enter("key".to_string());
+
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(5));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+        assert!(single_flight.lock_flights().is_empty());
+    }
+
+    #[test]
+    fn different_keys_should_not_block_each_other() {
+        let single_flight = SingleFlight::default();
+
+        let _first = single_flight.enter("first".to_string());
+        let _second = single_flight.enter("second".to_string());
+
+        assert_eq!(single_flight.lock_flights().len(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn one_task_should_be_in_flight_for_key() {
+        let single_flight = AsyncSingleFlight::default();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..8).map(|_| {
+            let single_flight = single_flight.clone();
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+
+            tokio::spawn(async move {
+                let _flight = single_flight.enter("key".to_string()).await;
+
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            })
+        }).collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+        assert!(single_flight.lock_flights().is_empty());
+    }
+}
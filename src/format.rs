@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+
+use crate::codec::CodecKind;
+use crate::compression::Compression;
+use crate::service::FileCacheItemMetadata;
+
+/// Layout of cache entries on disk. Both formats are always readable,
+/// so cache written with one format keeps working after switching to another.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageFormat {
+    /// Two files per item: `[ITEM]-cache.[EXTENSION]` payload and `[ITEM]-cache-metadata.json` metadata
+    #[default]
+    V1,
+
+    /// Single file per item `[ITEM]-entry.fkesh`: binary header with metadata and checksum, followed by payload.
+    /// Half of files and syscalls of `V1`.
+    V2,
+}
+
+/// Entry file (format v2) postfix
+pub const ENTRY_FILENAME_POSTFIX: &str = "entry.fkesh";
+
+/// First bytes of entry file
+pub const ENTRY_MAGIC: &[u8; 4] = b"FKSH";
+
+pub const ENTRY_FORMAT_VERSION: u8 = 2;
+
+/// Header flag of `FileCacheItemMetadata::relative_expiry`
+const RELATIVE_EXPIRY_FLAG: u8 = 0b1;
+
+/// Entry file header size in bytes, payload follows it.
+///
+/// Layout, integers are little-endian, `0` - not set:
+///
+/// | Offset | Size | Field                               |
+/// |--------|------|-------------------------------------|
+/// | 0      | 4    | magic `FKSH`                        |
+/// | 4      | 1    | format version (`2`)                |
+/// | 5      | 1    | codec                               |
+/// | 6      | 1    | compression                         |
+/// | 7      | 1    | flags, bit 0 - relative expiry      |
+/// | 8      | 4    | payload CRC-32                      |
+/// | 12     | 4    | header CRC-32, of all other bytes   |
+/// | 16     | 8    | created unix time                   |
+/// | 24     | 8    | expiration unix time                |
+/// | 32     | 8    | TTL in seconds                      |
+/// | 40     | 8    | last access unix time               |
+/// | 48     | 8    | grace period in seconds             |
+/// | 56     | 8    | compute time in milliseconds        |
+pub const ENTRY_HEADER_LEN: usize = 64;
+
+/// Entry file content: header and payload
+pub(crate) fn encode_entry(metadata: &FileCacheItemMetadata, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(ENTRY_HEADER_LEN + payload.len());
+
+    data.extend_from_slice(ENTRY_MAGIC);
+    data.push(ENTRY_FORMAT_VERSION);
+    data.push(get_codec_id(metadata.codec));
+    data.push(get_compression_id(metadata.compression));
+    data.push(if metadata.relative_expiry { RELATIVE_EXPIRY_FLAG } else { 0 });
+    data.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    // Header checksum, set when header is complete
+    data.extend_from_slice(&[0; 4]);
+
+    for value in [
+        metadata.created_unixtime,
+        metadata.expires_at.unwrap_or(0),
+        metadata.ttl_secs,
+        metadata.last_access_unixtime.unwrap_or(0),
+        metadata.grace_period_secs.unwrap_or(0),
+        metadata.compute_time_ms.unwrap_or(0),
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let header_crc = get_header_crc(&data);
+    data[12..16].copy_from_slice(&header_crc.to_le_bytes());
+
+    data.extend_from_slice(payload);
+
+    data
+}
+
+/// Metadata and payload of entry file, checksum is verified.
+/// Returns reason for invalid content.
+pub(crate) fn decode_entry(mut data: Vec<u8>) -> Result<(FileCacheItemMetadata, Vec<u8>), String> {
+    let (metadata, payload_crc) = decode_entry_header(&data)?;
+
+    let payload = data.split_off(ENTRY_HEADER_LEN);
+
+    if crc32fast::hash(&payload) != payload_crc {
+        return Err("payload checksum mismatch".to_string())
+    }
+
+    Ok((metadata, payload))
+}
+
+/// Metadata and payload checksum from entry file header (first `ENTRY_HEADER_LEN` bytes at least),
+/// header checksum is verified. Returns reason for invalid header.
+pub(crate) fn decode_entry_header(header: &[u8]) -> Result<(FileCacheItemMetadata, u32), String> {
+    if header.len() < ENTRY_HEADER_LEN {
+        return Err(format!("entry header is truncated, {} bytes", header.len()))
+    }
+
+    if &header[0..4] != ENTRY_MAGIC {
+        return Err("not an entry file, magic mismatch".to_string())
+    }
+
+    if header[4] != ENTRY_FORMAT_VERSION {
+        return Err(format!("unsupported entry format version {}", header[4]))
+    }
+
+    if get_header_crc(header) != u32::from_le_bytes(header[12..16].try_into().unwrap_or_default()) {
+        return Err("header checksum mismatch".to_string())
+    }
+
+    let codec = CodecKind::all().iter().copied().chain([CodecKind::Custom]).find(|codec| get_codec_id(*codec) == header[5])
+        .ok_or_else(|| format!("unknown codec {}, check enabled cargo features", header[5]))?;
+
+    let compression = Compression::all().iter().copied().find(|compression| get_compression_id(*compression) == header[6])
+        .ok_or_else(|| format!("unknown compression {}, check enabled cargo features", header[6]))?;
+
+    let payload_crc = u32::from_le_bytes(header[8..12].try_into().unwrap_or_default());
+
+    let read_u64 = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap_or_default());
+    let read_optional_u64 = |offset: usize| Some(read_u64(offset)).filter(|value| *value > 0);
+
+    let metadata = FileCacheItemMetadata {
+        created_unixtime: read_u64(16),
+        expires_at: read_optional_u64(24),
+        relative_expiry: header[7] & RELATIVE_EXPIRY_FLAG != 0,
+        ttl_secs: read_u64(32),
+        codec,
+        compression,
+        last_access_unixtime: read_optional_u64(40),
+        grace_period_secs: read_optional_u64(48),
+        compute_time_ms: read_optional_u64(56),
+    };
+
+    Ok((metadata, payload_crc))
+}
+
+/// Checksum of header bytes except header checksum itself
+fn get_header_crc(header: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&header[0..12]);
+    hasher.update(&header[16..ENTRY_HEADER_LEN]);
+    hasher.finalize()
+}
+
+/// Stable codec id for entry header, doesn't depend on enabled features
+fn get_codec_id(codec: CodecKind) -> u8 {
+    match codec {
+        CodecKind::Json => 1,
+        #[cfg(feature = "bincode")]
+        CodecKind::Bincode => 2,
+        #[cfg(feature = "msgpack")]
+        CodecKind::MessagePack => 3,
+        #[cfg(feature = "cbor")]
+        CodecKind::Cbor => 4,
+        CodecKind::Custom => 255,
+    }
+}
+
+/// Stable compression id for entry header, doesn't depend on enabled features
+fn get_compression_id(compression: Compression) -> u8 {
+    match compression {
+        Compression::None => 0,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => 1,
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => 2,
+    }
+}
+
+#[cfg(test)]
+mod entry_format_tests {
+    use crate::codec::CodecKind;
+    use crate::compression::Compression;
+    use crate::format::{decode_entry, decode_entry_header, encode_entry, ENTRY_HEADER_LEN};
+    use crate::service::FileCacheItemMetadata;
+
+    fn get_metadata() -> FileCacheItemMetadata {
+        FileCacheItemMetadata {
+            ttl_secs: 60,
+            created_unixtime: 1000,
+            expires_at: Some(1060),
+            relative_expiry: true,
+            codec: CodecKind::Json,
+            compression: Compression::None,
+            last_access_unixtime: Some(1010),
+            grace_period_secs: None,
+            compute_time_ms: Some(25),
+        }
+    }
+
+    #[test]
+    fn entry_should_be_decoded() {
+        let data = encode_entry(&get_metadata(), b"{\"login\":\"demo\"}");
+
+        assert_eq!(data.len(), ENTRY_HEADER_LEN + 16);
+        assert_eq!(&data[0..4], b"FKSH");
+
+        let (metadata, payload) = decode_entry(data).unwrap();
+
+        assert_eq!(payload, b"{\"login\":\"demo\"}");
+        assert_eq!(metadata.created_unixtime, 1000);
+        assert_eq!(metadata.expires_at, Some(1060));
+        assert_eq!(metadata.ttl_secs, 60);
+        assert!(metadata.relative_expiry);
+        assert_eq!(metadata.codec, CodecKind::Json);
+        assert_eq!(metadata.last_access_unixtime, Some(1010));
+        assert_eq!(metadata.grace_period_secs, None);
+        assert_eq!(metadata.compute_time_ms, Some(25));
+    }
+
+    #[test]
+    fn damaged_entry_should_be_rejected() {
+        let mut data = encode_entry(&get_metadata(), b"{\"login\":\"demo\"}");
+        let last = data.len() - 1;
+        data[last] = b']';
+
+        assert_eq!(decode_entry(data).err().as_deref(), Some("payload checksum mismatch"));
+
+        assert!(decode_entry_header(b"FKSH").is_err());
+        assert!(decode_entry_header(&[0; ENTRY_HEADER_LEN]).is_err());
+
+        let mut data = encode_entry(&get_metadata(), b"{}");
+        data[4] = 3;
+        assert!(decode_entry(data).err().is_some_and(|reason| reason.contains("version 3")));
+    }
+
+    #[test]
+    fn damaged_header_should_be_rejected() {
+        let mut data = encode_entry(&get_metadata(), b"{\"login\":\"demo\"}");
+
+        // Expiration unix time
+        data[24] ^= 0x01;
+
+        assert_eq!(decode_entry_header(&data).err().as_deref(), Some("header checksum mismatch"));
+        assert_eq!(decode_entry(data).err().as_deref(), Some("header checksum mismatch"));
+    }
+}
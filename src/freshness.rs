@@ -0,0 +1,9 @@
+/// Freshness of item returned by `FileCacheService::get_allow_stale`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Freshness {
+    /// Item isn't expired
+    Fresh,
+
+    /// Item is expired, but still in grace period
+    Stale,
+}
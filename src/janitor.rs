@@ -0,0 +1,71 @@
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+
+use log::info;
+
+/// Result of `FileCacheService::purge_expired`
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PurgeReport {
+    /// Expired items removed
+    pub expired_entries: usize,
+
+    /// Items with corrupted metadata removed
+    pub corrupted_entries: usize,
+
+    /// Cache or metadata files without companion file, temp files of interrupted writes
+    pub orphaned_files: usize,
+
+    /// Quarantined items removed after retention, see `FileCacheServiceBuilder::quarantine_retention`
+    pub quarantined_entries: usize,
+
+    pub bytes_freed: u64,
+}
+
+impl PurgeReport {
+    pub fn append(&mut self, other: &PurgeReport) {
+        self.expired_entries += other.expired_entries;
+        self.corrupted_entries += other.corrupted_entries;
+        self.orphaned_files += other.orphaned_files;
+        self.quarantined_entries += other.quarantined_entries;
+        self.bytes_freed += other.bytes_freed;
+    }
+}
+
+/// Background thread which runs `FileCacheService::purge_expired` on interval.
+///
+/// Created with `FileCacheService::spawn_janitor`. Thread is stopped with `shutdown` or on drop.
+pub struct Janitor {
+    stop_sender: Option<Sender<()>>,
+
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Janitor {
+    pub(crate) fn new(stop_sender: Sender<()>, handle: JoinHandle<()>) -> Janitor {
+        Janitor {
+            stop_sender: Some(stop_sender),
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop janitor thread and wait for current sweep to complete
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        // Dropped sender wakes up the thread
+        self.stop_sender.take();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+            info!("file cache janitor has been stopped");
+        }
+    }
+}
+
+impl Drop for Janitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
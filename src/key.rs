@@ -0,0 +1,133 @@
+use crate::error::FileCacheError;
+use crate::types::OperationResult;
+
+/// Max length of encoded key, leaves room for filename postfixes within 255 bytes filename limit
+pub const MAX_ENCODED_KEY_LENGTH: usize = 200;
+
+const ESCAPE_CHAR: char = '%';
+
+/// Encode namespace, item or instance name into safe file name.
+///
+/// ASCII letters, digits, `-`, `_` and `.` are kept as is, all other bytes are
+/// percent-encoded (`/` -> `%2F`). Leading `.` is encoded as well, so keys can't
+/// produce `.`, `..` or hidden files (reserved for service files like `.locks`).
+///
+/// Returns `FileCacheError::InvalidKey` if encoded key is longer than `MAX_ENCODED_KEY_LENGTH`.
+pub fn encode_key(key: &str) -> OperationResult<String> {
+    let mut encoded = String::with_capacity(key.len());
+
+    for (index, byte) in key.bytes().enumerate() {
+        let safe = byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' ||
+                        (byte == b'.' && index > 0);
+
+        if safe {
+            encoded.push(byte as char);
+
+        } else {
+            encoded.push_str(&format!("{}{:02X}", ESCAPE_CHAR, byte));
+        }
+    }
+
+    if encoded.len() > MAX_ENCODED_KEY_LENGTH {
+        return Err(FileCacheError::InvalidKey(
+            format!("key is too long ({} bytes encoded, max {})", encoded.len(), MAX_ENCODED_KEY_LENGTH)
+        ))
+    }
+
+    Ok(encoded)
+}
+
+/// Decode file name produced by `encode_key`.
+///
+/// Returns `None` for foreign file names, including names `encode_key` wouldn't produce
+/// (lowercase escapes, escaped safe chars): they would point to another file after re-encoding.
+pub fn decode_key(encoded: &str) -> Option<String> {
+    let bytes = encoded.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] == ESCAPE_CHAR as u8 {
+            let hex = encoded.get(index + 1..index + 3)?;
+
+            if !hex.bytes().all(|byte| byte.is_ascii_digit() || (b'A'..=b'F').contains(&byte)) {
+                return None
+            }
+
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+
+    let decoded = String::from_utf8(decoded).ok()?;
+
+    match encode_key(&decoded) {
+        Ok(reencoded) if reencoded == encoded => Some(decoded),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod encode_key_tests {
+    use crate::error::FileCacheError;
+    use crate::key::{decode_key, encode_key, MAX_ENCODED_KEY_LENGTH};
+
+    #[test]
+    fn safe_chars_should_be_kept() {
+        assert_eq!(encode_key("Demo_item-1.v2").unwrap(), "Demo_item-1.v2");
+    }
+
+    #[test]
+    fn path_separators_should_be_encoded() {
+        assert_eq!(encode_key("../../etc/x").unwrap(), "%2E.%2F..%2Fetc%2Fx");
+        assert_eq!(encode_key("a\\b").unwrap(), "a%5Cb");
+    }
+
+    #[test]
+    fn dot_names_should_be_encoded() {
+        assert_eq!(encode_key(".").unwrap(), "%2E");
+        assert_eq!(encode_key("..").unwrap(), "%2E.");
+        assert_eq!(encode_key(".locks").unwrap(), "%2Elocks");
+    }
+
+    #[test]
+    fn escape_char_should_be_encoded() {
+        assert_eq!(encode_key("100%").unwrap(), "100%25");
+    }
+
+    #[test]
+    fn return_invalid_key_error_for_too_long_key() {
+        let key = "a".repeat(MAX_ENCODED_KEY_LENGTH + 1);
+
+        match encode_key(&key) {
+            Err(FileCacheError::InvalidKey(_)) => {}
+            _ => panic!("invalid key error expected")
+        }
+    }
+
+    #[test]
+    fn decoded_key_should_be_equal_to_original() {
+        for key in ["demo", "../../etc/x", ".hidden", "100%", "ключ / 键", "a b+c"] {
+            assert_eq!(decode_key(&encode_key(key).unwrap()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn return_none_for_invalid_escape_sequence() {
+        assert!(decode_key("abc%2").is_none());
+        assert!(decode_key("abc%ZZ").is_none());
+        assert!(decode_key("abc%+F").is_none());
+    }
+
+    #[test]
+    fn return_none_for_non_canonical_key() {
+        assert!(decode_key("a%2fb").is_none());
+        assert!(decode_key("%61bc").is_none());
+        assert!(decode_key(".hidden").is_none());
+        assert_eq!(decode_key("a%2Fb").unwrap(), "a/b");
+    }
+}
@@ -1,6 +1,26 @@
 pub mod service;
 pub mod error;
 pub mod types;
+pub mod key;
+pub mod codec;
+pub mod compression;
+pub mod limits;
+pub mod expiry;
+pub mod lookup;
+pub mod freshness;
+pub mod entry;
+pub mod listing;
+pub mod format;
+pub mod corruption;
+pub mod config;
+pub mod policy;
+pub mod clock;
+pub mod janitor;
+
+mod file;
+mod flight;
+mod memory;
+mod lock;
 
 #[cfg(test)]
 pub mod tests;
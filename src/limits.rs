@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// Cache size limits. `None` - unlimited.
+///
+/// Applied to whole cache instance or to single namespace.
+/// When `store` exceeds limits, least recently used items are evicted.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct CacheLimits {
+    /// Max total size of cache and metadata files in bytes
+    #[serde(default)]
+    pub max_total_bytes: Option<u64>,
+
+    /// Max items count
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+}
+
+impl CacheLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_total_bytes.is_none() && self.max_entries.is_none()
+    }
+
+    pub fn is_exceeded(&self, total_bytes: u64, entries: usize) -> bool {
+        self.max_total_bytes.is_some_and(|max| total_bytes > max) ||
+            self.max_entries.is_some_and(|max| entries > max)
+    }
+}
+
+#[cfg(test)]
+mod cache_limits_tests {
+    use crate::limits::CacheLimits;
+
+    #[test]
+    fn default_limits_should_be_unlimited() {
+        let limits = CacheLimits::default();
+
+        assert!(limits.is_unlimited());
+        assert!(!limits.is_exceeded(u64::MAX, usize::MAX));
+    }
+
+    #[test]
+    fn limits_should_be_exceeded_by_any_value() {
+        let limits = CacheLimits { max_total_bytes: Some(100), max_entries: Some(2) };
+
+        assert!(!limits.is_exceeded(100, 2));
+        assert!(limits.is_exceeded(101, 1));
+        assert!(limits.is_exceeded(10, 3));
+    }
+}
@@ -0,0 +1,7 @@
+/// Options of `FileCacheService::keys_with` and `FileCacheService::iter_with`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ListOptions {
+    /// List expired items too (they are removed by `get` and `purge_expired` only).
+    /// Default: `false`
+    pub include_expired: bool,
+}
@@ -0,0 +1,259 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use fs2::{FileExt, lock_contended_error};
+use log::debug;
+
+use crate::error::FileCacheError;
+use crate::types::{EmptyResult, OperationResult, OptionalResult};
+
+pub const LOCK_FILENAME_POSTFIX: &str = "lock";
+
+/// How often lock with timeout is retried
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Advisory file lock (`flock` on unix, `LockFileEx` on windows).
+///
+/// Works across processes and threads: each lock opens its own file handle.
+/// Lock is released on drop.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Acquire shared (read) lock. Blocks until available, or up to `timeout` if set.
+    pub fn shared(lock_file_path: &Path, timeout: Option<Duration>) -> OperationResult<FileLock> {
+        let file = open_lock_file(lock_file_path)?;
+
+        match timeout {
+            Some(timeout) => wait_for_lock(lock_file_path, timeout, || FileExt::try_lock_shared(&file))?,
+            None => file.lock_shared()?
+        }
+
+        debug!("shared lock acquired '{}'", lock_file_path.display());
+        Ok(FileLock { file })
+    }
+
+    /// Acquire exclusive (write) lock. Blocks until available, or up to `timeout` if set.
+    pub fn exclusive(lock_file_path: &Path, timeout: Option<Duration>) -> OperationResult<FileLock> {
+        let file = open_lock_file(lock_file_path)?;
+
+        match timeout {
+            Some(timeout) => wait_for_lock(lock_file_path, timeout, || FileExt::try_lock_exclusive(&file))?,
+            None => file.lock_exclusive()?
+        }
+
+        debug!("exclusive lock acquired '{}'", lock_file_path.display());
+        Ok(FileLock { file })
+    }
+
+    /// Acquire exclusive (write) lock only if available right away, `None` if lock is held
+    pub fn try_exclusive(lock_file_path: &Path) -> OptionalResult<FileLock> {
+        let file = open_lock_file(lock_file_path)?;
+        try_lock_exclusive(lock_file_path, file)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl FileLock {
+    /// Acquire shared (read) lock without blocking async runtime: lock is polled until available,
+    /// or up to `timeout` if set.
+    pub async fn shared_async(lock_file_path: &Path, timeout: Option<Duration>) -> OperationResult<FileLock> {
+        let file = open_lock_file_async(lock_file_path).await?;
+
+        poll_for_lock(lock_file_path, timeout, || FileExt::try_lock_shared(&file)).await?;
+
+        debug!("shared lock acquired '{}'", lock_file_path.display());
+        Ok(FileLock { file })
+    }
+
+    /// Acquire exclusive (write) lock without blocking async runtime, see `shared_async`
+    pub async fn exclusive_async(lock_file_path: &Path, timeout: Option<Duration>) -> OperationResult<FileLock> {
+        let file = open_lock_file_async(lock_file_path).await?;
+
+        poll_for_lock(lock_file_path, timeout, || FileExt::try_lock_exclusive(&file)).await?;
+
+        debug!("exclusive lock acquired '{}'", lock_file_path.display());
+        Ok(FileLock { file })
+    }
+
+    /// Async version of `try_exclusive`
+    pub async fn try_exclusive_async(lock_file_path: &Path) -> OptionalResult<FileLock> {
+        let file = open_lock_file_async(lock_file_path).await?;
+        try_lock_exclusive(lock_file_path, file)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+fn try_lock_exclusive(lock_file_path: &Path, file: File) -> OptionalResult<FileLock> {
+    match FileExt::try_lock_exclusive(&file) {
+        Ok(()) => {
+            debug!("exclusive lock acquired '{}'", lock_file_path.display());
+            Ok(Some(FileLock { file }))
+        }
+        Err(e) if e.raw_os_error() == lock_contended_error().raw_os_error() => Ok(None),
+        Err(e) => Err(FileCacheError::from(e))
+    }
+}
+
+/// Poll `try_lock` until it succeeds or `timeout` passes
+fn wait_for_lock(lock_file_path: &Path, timeout: Duration,
+                 try_lock: impl Fn() -> io::Result<()>) -> EmptyResult {
+    let started = Instant::now();
+
+    loop {
+        match try_lock() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.raw_os_error() == lock_contended_error().raw_os_error() => {
+                if started.elapsed() >= timeout {
+                    return Err(FileCacheError::LockTimeout {
+                        path: lock_file_path.to_path_buf(),
+                        timeout,
+                    })
+                }
+
+                thread::sleep(LOCK_POLL_INTERVAL);
+            }
+            Err(e) => return Err(FileCacheError::from(e))
+        }
+    }
+}
+
+/// Async version of `wait_for_lock`, `None` timeout - wait forever
+#[cfg(feature = "tokio")]
+async fn poll_for_lock(lock_file_path: &Path, timeout: Option<Duration>,
+                       try_lock: impl Fn() -> io::Result<()>) -> EmptyResult {
+    let started = Instant::now();
+
+    loop {
+        match try_lock() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.raw_os_error() == lock_contended_error().raw_os_error() => {
+                if let Some(timeout) = timeout.filter(|timeout| started.elapsed() >= *timeout) {
+                    return Err(FileCacheError::LockTimeout {
+                        path: lock_file_path.to_path_buf(),
+                        timeout,
+                    })
+                }
+
+                tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+            }
+            Err(e) => return Err(FileCacheError::from(e))
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn open_lock_file_async(lock_file_path: &Path) -> OperationResult<File> {
+    let file = tokio::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_file_path).await?;
+    Ok(file.into_std().await)
+}
+
+fn open_lock_file(lock_file_path: &Path) -> OperationResult<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_file_path)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod file_lock_tests {
+    use std::sync::{Arc, Barrier};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use tempfile::tempdir;
+
+    use crate::error::FileCacheError;
+    use crate::lock::FileLock;
+
+    #[test]
+    fn exclusive_lock_should_wait_for_shared_lock_release() {
+        let dir = tempdir().unwrap();
+        let lock_file_path = dir.path().join("demo.lock");
+
+        let shared_lock = FileLock::shared(&lock_file_path, None).unwrap();
+
+        let released = Arc::new(AtomicBool::new(false));
+        let barrier = Arc::new(Barrier::new(2));
+
+        let handle = {
+            let lock_file_path = lock_file_path.clone();
+            let released = released.clone();
+            let barrier = barrier.clone();
+
+            thread::spawn(move || {
+                barrier.wait();
+                let _lock = FileLock::exclusive(&lock_file_path, None).unwrap();
+                released.load(Ordering::SeqCst)
+            })
+        };
+
+        barrier.wait();
+        thread::sleep(Duration::from_millis(200));
+
+        released.store(true, Ordering::SeqCst);
+        drop(shared_lock);
+
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn shared_locks_should_not_block_each_other() {
+        let dir = tempdir().unwrap();
+        let lock_file_path = dir.path().join("demo.lock");
+
+        let _first = FileLock::shared(&lock_file_path, None).unwrap();
+        let _second = FileLock::shared(&lock_file_path, None).unwrap();
+    }
+
+    #[test]
+    fn exclusive_lock_with_timeout_should_fail_while_shared_lock_is_held() {
+        let dir = tempdir().unwrap();
+        let lock_file_path = dir.path().join("demo.lock");
+
+        let _shared_lock = FileLock::shared(&lock_file_path, None).unwrap();
+
+        match FileLock::exclusive(&lock_file_path, Some(Duration::from_millis(100))) {
+            Err(FileCacheError::LockTimeout { path, .. }) => assert_eq!(path, lock_file_path),
+            _ => panic!("lock timeout error expected")
+        }
+    }
+
+    #[test]
+    fn lock_with_timeout_should_be_acquired_if_free() {
+        let dir = tempdir().unwrap();
+        let lock_file_path = dir.path().join("demo.lock");
+
+        assert!(FileLock::exclusive(&lock_file_path, Some(Duration::from_millis(100))).is_ok());
+    }
+
+    #[test]
+    fn try_exclusive_should_not_wait_for_held_lock() {
+        let dir = tempdir().unwrap();
+        let lock_file_path = dir.path().join("demo.lock");
+
+        let shared_lock = FileLock::shared(&lock_file_path, None).unwrap();
+        assert!(FileLock::try_exclusive(&lock_file_path).unwrap().is_none());
+
+        drop(shared_lock);
+        assert!(FileLock::try_exclusive(&lock_file_path).unwrap().is_some());
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use crate::error::FileCacheError;
+use crate::types::OptionalResult;
+
+/// Result of `FileCacheService::get_detailed`: item or the reason why it wasn't returned.
+#[derive(Debug)]
+pub enum CacheLookup<T> {
+    Found(T),
+
+    /// Item wasn't stored, was removed or only part of its files exists (interrupted write)
+    Missing,
+
+    Expired,
+
+    /// Metadata or cache file can't be read. Item has been handled according to `CorruptionPolicy`.
+    Corrupted {
+        path: PathBuf,
+        reason: String,
+    },
+
+    /// Cache file is valid, but doesn't match requested type (e.g. struct has been changed).
+    /// Item has been handled according to `CorruptionPolicy`.
+    ///
+    /// Codecs which aren't self-describing (bincode) can't tell it from corruption,
+    /// `Corrupted` is reported for them.
+    TypeMismatch {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl<T> CacheLookup<T> {
+    pub fn is_found(&self) -> bool {
+        matches!(self, CacheLookup::Found(_))
+    }
+
+    /// Item if found
+    pub fn found(self) -> Option<T> {
+        match self {
+            CacheLookup::Found(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// Map found item with `f`
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> CacheLookup<U> {
+        match self {
+            CacheLookup::Found(value) => CacheLookup::Found(f(value)),
+            CacheLookup::Missing => CacheLookup::Missing,
+            CacheLookup::Expired => CacheLookup::Expired,
+            CacheLookup::Corrupted { path, reason } => CacheLookup::Corrupted { path, reason },
+            CacheLookup::TypeMismatch { path, reason } => CacheLookup::TypeMismatch { path, reason },
+        }
+    }
+
+    /// Strict variant of `found`: `Corrupted` and `TypeMismatch` become
+    /// `FileCacheError::Corrupted` and `FileCacheError::SchemaMismatch` errors.
+    pub fn into_result(self) -> OptionalResult<T> {
+        match self {
+            CacheLookup::Found(value) => Ok(Some(value)),
+            CacheLookup::Missing | CacheLookup::Expired => Ok(None),
+            CacheLookup::Corrupted { path, reason } => Err(FileCacheError::Corrupted { path, reason }),
+            CacheLookup::TypeMismatch { path, reason } => Err(FileCacheError::SchemaMismatch { path, reason }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod cache_lookup_tests {
+    use std::path::PathBuf;
+
+    use crate::error::FileCacheError;
+    use crate::lookup::CacheLookup;
+
+    #[test]
+    fn missing_and_expired_should_be_none() {
+        assert!(CacheLookup::<u8>::Missing.into_result().unwrap().is_none());
+        assert!(CacheLookup::<u8>::Expired.into_result().unwrap().is_none());
+    }
+
+    #[test]
+    fn map_should_keep_reason() {
+        assert_eq!(CacheLookup::Found(2).map(|value| value * 10).found(), Some(20));
+
+        let lookup = CacheLookup::<u8>::Corrupted {
+            path: PathBuf::from("demo-cache.json"),
+            reason: "invalid data".to_string()
+        };
+
+        assert!(matches!(lookup.map(|value| value * 10), CacheLookup::Corrupted { reason, .. } if reason == "invalid data"));
+    }
+
+    #[test]
+    fn type_mismatch_should_be_schema_mismatch_error() {
+        let lookup = CacheLookup::<u8>::TypeMismatch {
+            path: PathBuf::from("demo-cache.json"),
+            reason: "invalid type".to_string()
+        };
+
+        match lookup.into_result() {
+            Err(FileCacheError::SchemaMismatch { path, .. }) => assert_eq!(path, PathBuf::from("demo-cache.json")),
+            _ => panic!("schema mismatch error expected")
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use log::debug;
+
+use crate::error::FileCacheError;
+use crate::limits::CacheLimits;
+use crate::service::FileCacheItemMetadata;
+use crate::types::OptionalResult;
+
+/// Identity of file version: file with metadata is replaced with atomic rename on each write,
+/// so another process write changes its modification time, size or inode.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    #[cfg(unix)]
+    inode: u64,
+}
+
+impl FileStamp {
+    /// Stamp of file, `None` if file doesn't exist
+    pub fn read(file_path: &Path) -> OptionalResult<FileStamp> {
+        FileStamp::from_metadata(std::fs::metadata(file_path))
+    }
+
+    /// Stamp of file, `None` if file doesn't exist
+    #[cfg(feature = "tokio")]
+    pub async fn read_async(file_path: &Path) -> OptionalResult<FileStamp> {
+        FileStamp::from_metadata(tokio::fs::metadata(file_path).await)
+    }
+
+    fn from_metadata(metadata: std::io::Result<std::fs::Metadata>) -> OptionalResult<FileStamp> {
+        match metadata {
+            Ok(metadata) => Ok(Some(FileStamp {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+                #[cfg(unix)]
+                inode: std::os::unix::fs::MetadataExt::ino(&metadata),
+            })),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(FileCacheError::from(e))
+        }
+    }
+}
+
+/// In-memory LRU tier in front of file cache: cache file content and metadata of recently read items,
+/// key - path of file with metadata: metadata file or entry file (`StorageFormat::V2`).
+///
+/// Entry is valid while the file has the same `FileStamp`.
+pub struct MemoryTier {
+    limits: CacheLimits,
+    state: Mutex<MemoryTierState>,
+}
+
+#[derive(Default)]
+struct MemoryTierState {
+    entries: HashMap<PathBuf, MemoryEntry>,
+
+    /// Key - use counter, value - entry key. First is least recently used.
+    recency: BTreeMap<u64, PathBuf>,
+
+    use_counter: u64,
+
+    /// Total size of cache file contents in bytes
+    total_bytes: u64,
+}
+
+struct MemoryEntry {
+    stamp: FileStamp,
+    metadata: FileCacheItemMetadata,
+    data: Vec<u8>,
+    last_use: u64,
+}
+
+impl MemoryTier {
+    pub fn new(limits: CacheLimits) -> MemoryTier {
+        MemoryTier { limits, state: Mutex::new(MemoryTierState::default()) }
+    }
+
+    /// Metadata and cache file content, `None` if missing or metadata file has been replaced
+    pub fn get(&self, metadata_file_path: &Path, stamp: &FileStamp) -> Option<(FileCacheItemMetadata, Vec<u8>)> {
+        let mut state = self.lock_state();
+
+        if state.entries.get(metadata_file_path)?.stamp != *stamp {
+            debug!("memory tier entry '{}' is outdated", metadata_file_path.display());
+            state.remove(metadata_file_path);
+            return None
+        }
+
+        let entry = state.touch(metadata_file_path)?;
+        Some((entry.metadata.clone(), entry.data.clone()))
+    }
+
+    /// Put entry, least recently used entries are evicted if limits are exceeded
+    pub fn insert(&self, metadata_file_path: PathBuf, stamp: FileStamp, metadata: FileCacheItemMetadata, data: Vec<u8>) {
+        let mut state = self.lock_state();
+
+        state.remove(&metadata_file_path);
+
+        if self.limits.is_exceeded(data.len() as u64, 1) {
+            debug!("item '{}' is too large for memory tier", metadata_file_path.display());
+            return
+        }
+
+        state.use_counter += 1;
+        let last_use = state.use_counter;
+
+        state.total_bytes += data.len() as u64;
+        state.recency.insert(last_use, metadata_file_path.clone());
+        state.entries.insert(metadata_file_path, MemoryEntry { stamp, metadata, data, last_use });
+
+        while self.limits.is_exceeded(state.total_bytes, state.entries.len()) {
+            let Some((_, evicted_path)) = state.recency.pop_first() else { break };
+
+            if let Some(evicted) = state.entries.remove(&evicted_path) {
+                state.total_bytes -= evicted.data.len() as u64;
+                debug!("memory tier entry '{}' has been evicted", evicted_path.display());
+            }
+        }
+    }
+
+    /// Replace metadata of existing entry, e.g. after metadata file has been rewritten on access
+    pub fn update_metadata(&self, metadata_file_path: &Path, stamp: FileStamp, metadata: FileCacheItemMetadata) {
+        let mut state = self.lock_state();
+
+        if let Some(entry) = state.entries.get_mut(metadata_file_path) {
+            entry.stamp = stamp;
+            entry.metadata = metadata;
+        }
+    }
+
+    pub fn remove(&self, metadata_file_path: &Path) {
+        self.lock_state().remove(metadata_file_path);
+    }
+
+    /// Remove entries of files inside `dir`
+    pub fn remove_dir(&self, dir: &Path) {
+        let mut state = self.lock_state();
+
+        let paths: Vec<PathBuf> = state.entries.keys().filter(|path| path.starts_with(dir)).cloned().collect();
+
+        for path in paths {
+            state.remove(&path);
+        }
+    }
+
+    pub fn clear(&self) {
+        *self.lock_state() = MemoryTierState::default();
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, MemoryTierState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl MemoryTierState {
+    /// Mark entry as most recently used
+    fn touch(&mut self, path: &Path) -> Option<&MemoryEntry> {
+        self.use_counter += 1;
+        let last_use = self.use_counter;
+
+        let entry = self.entries.get_mut(path)?;
+        self.recency.remove(&entry.last_use);
+        self.recency.insert(last_use, path.to_path_buf());
+        entry.last_use = last_use;
+
+        Some(entry)
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.recency.remove(&entry.last_use);
+            self.total_bytes -= entry.data.len() as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod memory_tier_tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use tempfile::tempdir;
+
+    use crate::codec::CodecKind;
+    use crate::compression::Compression;
+    use crate::limits::CacheLimits;
+    use crate::memory::{FileStamp, MemoryTier};
+    use crate::service::FileCacheItemMetadata;
+
+    fn get_metadata() -> FileCacheItemMetadata {
+        FileCacheItemMetadata {
+            ttl_secs: 0,
+            created_unixtime: 1000,
+            expires_at: None,
+            relative_expiry: false,
+            codec: CodecKind::Json,
+            compression: Compression::None,
+            last_access_unixtime: None,
+            grace_period_secs: None,
+            compute_time_ms: None,
+        }
+    }
+
+    fn get_stamp(path: &Path) -> FileStamp {
+        fs::write(path, "{}").unwrap();
+        FileStamp::read(path).unwrap().unwrap()
+    }
+
+    #[test]
+    fn least_recently_used_entry_should_be_evicted() {
+        let tmp = tempdir().unwrap();
+        let stamp = get_stamp(&tmp.path().join("item"));
+
+        let memory_tier = MemoryTier::new(CacheLimits { max_total_bytes: None, max_entries: Some(2) });
+
+        for name in ["first", "second"] {
+            memory_tier.insert(PathBuf::from(name), stamp.clone(), get_metadata(), vec![0; 8]);
+        }
+
+        assert!(memory_tier.get(Path::new("first"), &stamp).is_some());
+
+        memory_tier.insert(PathBuf::from("third"), stamp.clone(), get_metadata(), vec![0; 8]);
+
+        assert!(memory_tier.get(Path::new("first"), &stamp).is_some());
+        assert!(memory_tier.get(Path::new("second"), &stamp).is_none());
+        assert!(memory_tier.get(Path::new("third"), &stamp).is_some());
+    }
+
+    #[test]
+    fn entries_should_be_limited_by_bytes() {
+        let tmp = tempdir().unwrap();
+        let stamp = get_stamp(&tmp.path().join("item"));
+
+        let memory_tier = MemoryTier::new(CacheLimits { max_total_bytes: Some(16), max_entries: None });
+
+        memory_tier.insert(PathBuf::from("first"), stamp.clone(), get_metadata(), vec![0; 10]);
+        memory_tier.insert(PathBuf::from("second"), stamp.clone(), get_metadata(), vec![0; 10]);
+        memory_tier.insert(PathBuf::from("large"), stamp.clone(), get_metadata(), vec![0; 20]);
+
+        assert!(memory_tier.get(Path::new("first"), &stamp).is_none());
+        assert!(memory_tier.get(Path::new("second"), &stamp).is_some());
+        assert!(memory_tier.get(Path::new("large"), &stamp).is_none());
+    }
+
+    #[test]
+    fn entry_should_be_outdated_after_file_replace() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("item");
+        let stamp = get_stamp(&path);
+
+        let memory_tier = MemoryTier::new(CacheLimits { max_total_bytes: None, max_entries: Some(10) });
+        memory_tier.insert(path.clone(), stamp.clone(), get_metadata(), vec![1, 2, 3]);
+
+        let (_, data) = memory_tier.get(&path, &stamp).unwrap();
+        assert_eq!(data, vec![1, 2, 3]);
+
+        let temp_path = tmp.path().join("item.tmp");
+        fs::write(&temp_path, "{\"a\":1}").unwrap();
+        fs::rename(&temp_path, &path).unwrap();
+
+        let new_stamp = FileStamp::read(&path).unwrap().unwrap();
+        assert_ne!(new_stamp, stamp);
+
+        assert!(memory_tier.get(&path, &new_stamp).is_none());
+        assert!(memory_tier.get(&path, &stamp).is_none());
+
+        memory_tier.insert(path.clone(), new_stamp.clone(), get_metadata(), vec![4]);
+        memory_tier.remove_dir(tmp.path());
+        assert!(memory_tier.get(&path, &new_stamp).is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::CodecKind;
+use crate::compression::Compression;
+use crate::config::{invalid_config, validate_limits};
+use crate::expiry::Expiry;
+use crate::limits::CacheLimits;
+use crate::types::EmptyResult;
+
+/// Namespace defaults, see `FileCacheService::set_namespace_policy`.
+///
+/// `None` - instance setting is used.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct NamespacePolicy {
+    /// Expiry of items stored with `store_default`
+    #[serde(default)]
+    pub default_ttl_secs: Option<u64>,
+
+    /// Namespace limits, replace limits set with `FileCacheServiceBuilder::namespace_limits`
+    #[serde(default, flatten)]
+    pub limits: CacheLimits,
+
+    /// Codec for new items
+    #[serde(default)]
+    pub codec: Option<CodecKind>,
+
+    /// Compression for new items
+    #[serde(default)]
+    pub compression: Option<Compression>,
+
+    /// Grace period of new items in seconds, see `FileCacheService::get_allow_stale`
+    #[serde(default)]
+    pub grace_period_secs: Option<u64>,
+}
+
+impl NamespacePolicy {
+    /// Expiry for `store_default`, `None` - instance default
+    pub fn get_default_expiry(&self) -> Option<Expiry> {
+        self.default_ttl_secs.map(|ttl_secs| Expiry::After(Duration::from_secs(ttl_secs)))
+    }
+
+    /// Returns `FileCacheError::InvalidConfig` for settings which can't work
+    pub fn validate(&self) -> EmptyResult {
+        if self.default_ttl_secs == Some(0) {
+            return Err(invalid_config("default_ttl_secs is 0, items would expire right away"))
+        }
+
+        if self.codec == Some(CodecKind::Custom) {
+            return Err(invalid_config("codec can't be custom, custom codec is passed to store_with_codec"))
+        }
+
+        validate_limits("limits", &self.limits)
+    }
+}
+
+#[cfg(test)]
+mod namespace_policy_tests {
+    use crate::codec::CodecKind;
+    use crate::error::FileCacheError;
+    use crate::limits::CacheLimits;
+    use crate::policy::NamespacePolicy;
+
+    #[test]
+    fn limits_should_be_flattened() {
+        let policy: NamespacePolicy = serde_json::from_str(
+            r#"{"default_ttl_secs": 300, "max_entries": 1000, "codec": "json"}"#).unwrap();
+
+        assert_eq!(policy.default_ttl_secs, Some(300));
+        assert_eq!(policy.limits, CacheLimits { max_total_bytes: None, max_entries: Some(1000) });
+        assert_eq!(policy.codec, Some(CodecKind::Json));
+        assert!(policy.compression.is_none());
+    }
+
+    #[test]
+    fn zero_ttl_should_be_rejected() {
+        let policy = NamespacePolicy { default_ttl_secs: Some(0), ..NamespacePolicy::default() };
+        assert!(matches!(policy.validate(), Err(FileCacheError::InvalidConfig(_))));
+    }
+}
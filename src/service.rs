@@ -1,45 +1,296 @@
-use std::fs;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
 
 use log::{debug, error, info};
 use non_blank_string_rs::NonBlankString;
 use serde::{Deserialize, Serialize};
-use serde::de::DeserializeOwned;
+use serde::de::{DeserializeOwned, IgnoredAny};
 
+use crate::clock::{Clock, SystemClock};
+use crate::codec::{Codec, CodecKind, CustomPayload};
+use crate::compression::Compression;
+use crate::corruption::CorruptionPolicy;
 use crate::error::FileCacheError;
+use crate::expiry::Expiry;
+use crate::key::encode_key;
+#[cfg(feature = "tokio")]
+use crate::flight::AsyncSingleFlight;
+use crate::flight::SingleFlight;
+use crate::memory::{FileStamp, MemoryTier};
+use crate::file::{get_file_size, persist_temp_file, sync_dir, write_file_atomically, write_temp_file, WriteOptions};
+use crate::format::{decode_entry, decode_entry_header, encode_entry, StorageFormat, ENTRY_FILENAME_POSTFIX, ENTRY_HEADER_LEN};
+use crate::limits::CacheLimits;
+use crate::lookup::CacheLookup;
+use crate::policy::NamespacePolicy;
+use crate::lock::{FileLock, LOCK_FILENAME_POSTFIX};
 use crate::types::{EmptyResult, OperationResult, OptionalResult};
 
+#[cfg(feature = "tokio")]
+mod async_service;
+mod builder;
+mod eviction;
+mod iter;
+mod stale;
+mod memoize;
+mod metadata;
+mod namespace;
+mod policy;
+mod purge;
+mod quarantine;
+mod scan;
+mod touch;
+
+#[cfg(feature = "tokio")]
+pub use async_service::AsyncFileCacheService;
+pub use builder::FileCacheServiceBuilder;
+pub use iter::CacheItems;
+pub use namespace::Namespace;
+pub use policy::POLICIES_DIRNAME;
+pub use quarantine::{QUARANTINE_DIRNAME, QUARANTINE_REASON_FILENAME};
+use eviction::{StoredSize, UsageEstimates};
+use policy::PolicyCache;
+
 /// # File cache service
 ///
 /// Supports structs with serde's `Serialize` and `Deserialize` traits.
-/// Non thread-safe.
+///
+/// ## Concurrency
+///
+/// Service is `Send + Sync` and safe to share between threads and processes.
+/// Each namespace is guarded by advisory file lock (`[INSTANCE NAME]/.locks/[NAMESPACE].lock`):
+/// `get` takes shared lock, `store`, `remove` and expired/corrupted items cleanup take exclusive lock.
 ///
 /// ## Storage hierarchy:
 ///
-/// Entity file path `[CACHE BASE DIR]/[INSTANCE NAME]/[NAMESPACE]/[ITEM-NAME]-cache.json`
+/// Entity file path `[CACHE BASE DIR]/[INSTANCE NAME]/[NAMESPACE]/[ITEM-NAME]-cache.[CODEC EXTENSION]`
 /// Entity metadata-file path `[CACHE BASE DIR]/[INSTANCE NAME]/[NAMESPACE]/[ITEM-NAME]-cache-metadata.json`
 ///
+/// With `StorageFormat::V2` single entry file `[CACHE BASE DIR]/[INSTANCE NAME]/[NAMESPACE]/[ITEM-NAME]-entry.fkesh`
+/// replaces both files.
+///
 /// ## Storage format
 ///
-/// Data format: `JSON`
+/// Data format: `JSON` by default, see `CodecKind` for other formats (cargo features).
+/// Metadata format: `JSON`, or binary header of entry file (`StorageFormat::V2`)
 #[derive(Clone)]
 pub struct FileCacheService {
-    /// Path to cache directory
-    root_path: String,
-
     instance_name: String,
+
+    /// Path to instance directory: `[CACHE BASE DIR]/[INSTANCE NAME]`
+    instance_path: PathBuf,
+
+    /// Codec for new items
+    codec: CodecKind,
+
+    /// Compression for new items
+    compression: Compression,
+
+    /// Instance limits
+    limits: CacheLimits,
+
+    /// Namespace limits, key - namespace
+    namespace_limits: HashMap<String, CacheLimits>,
+
+    /// Usage estimates for limits enforcement, shared by clones
+    usage_estimates: UsageEstimates,
+
+    /// Namespace policies read from policy files, shared by clones
+    policy_cache: PolicyCache,
+
+    /// Time source for expiration and metadata timestamps
+    clock: Arc<dyn Clock>,
+
+    /// Max time to wait for namespace lock, `None` - wait forever
+    lock_timeout: Option<Duration>,
+
+    corruption_policy: CorruptionPolicy,
+
+    /// Quarantined items older than this are removed by `purge_expired`, `None` - kept
+    quarantine_retention_secs: Option<u64>,
+
+    /// Expiry for `store_default`
+    default_expiry: Expiry,
+
+    /// Push expiration forward on each successful `get`
+    sliding_expiration: bool,
+
+    /// Grace period of new items, see `get_allow_stale`
+    grace_period_secs: Option<u64>,
+
+    /// Early refresh in `get_or_insert_with` (XFetch), `None` - disabled
+    early_refresh_beta: Option<f64>,
+
+    /// In-memory tier in front of files, shared by clones. `None` - disabled
+    memory_tier: Option<Arc<MemoryTier>>,
+
+    /// Computations of `get_or_insert_with` in flight, shared by clones
+    single_flight: SingleFlight,
+
+    /// Computations of `AsyncFileCacheService::get_or_insert_with` in flight, shared by clones
+    #[cfg(feature = "tokio")]
+    async_single_flight: AsyncSingleFlight,
+
+    /// Single-flight of `get_or_insert_with` across processes with item lock file
+    cross_process_single_flight: bool,
+
+    /// Layout of new items, both formats are readable
+    storage_format: StorageFormat,
+
+    write_options: WriteOptions,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct FileCacheItemMetadata {
+    /// Time to live in seconds, `0` - immortal. Kept for readers without `expires_at` support,
+    /// so it's at least `1` for items which expire (see `get_legacy_ttl_secs`).
     pub ttl_secs: u64,
     pub created_unixtime: u64,
+
+    /// Expiration unix time, `None` - never expires.
+    /// Missing in metadata written before `Expiry` support, `ttl_secs` is used then.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+
+    /// Expiry was set as duration since store (`Expiry::After`), only such items slide,
+    /// see `FileCacheServiceBuilder::sliding_expiration`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub relative_expiry: bool,
+
+    /// Codec of cache file. Missing in metadata written before codecs support (JSON).
+    #[serde(default)]
+    pub codec: CodecKind,
+
+    /// Compression of cache file. Missing in metadata written before compression support.
+    #[serde(default)]
+    pub compression: Compression,
+
+    /// Last successful `get` time, tracked only when cache limits are set (see `CacheLimits`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_access_unixtime: Option<u64>,
+
+    /// Time after expiration while item is kept and can be read as stale (see `get_allow_stale`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_period_secs: Option<u64>,
+
+    /// Time it took to compute item in milliseconds, recorded by `get_or_insert_with`.
+    /// Used for early refresh, see `FileCacheServiceBuilder::early_refresh`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_time_ms: Option<u64>,
+}
+
+impl FileCacheItemMetadata {
+    /// Expiration unix time, `None` - never expires
+    pub fn get_expires_at_unixtime(&self) -> Option<u64> {
+        match self.expires_at {
+            Some(expires_at) => Some(expires_at),
+            None if self.ttl_secs > 0 => Some(self.created_unixtime.saturating_add(self.ttl_secs)),
+            None => None
+        }
+    }
+
+    /// Item is expired from `expires_at` second on
+    pub fn is_expired(&self, now_unixtime: u64) -> bool {
+        self.get_expires_at_unixtime().is_some_and(|expires_at| now_unixtime >= expires_at)
+    }
+
+    /// Item is expired and its grace period is over, so it can't be read as stale and can be removed
+    pub fn is_grace_period_over(&self, now_unixtime: u64) -> bool {
+        self.get_expires_at_unixtime().is_some_and(|expires_at|
+            now_unixtime >= expires_at.saturating_add(self.grace_period_secs.unwrap_or(0)))
+    }
+
+    /// Expiration unix time for sliding expiration: TTL set on store (`ttl_secs`) counted from `now_unixtime`.
+    ///
+    /// Items with absolute expiry (`Expiry::At`) keep their expiration, `None` for items which never expire.
+    pub fn get_sliding_expires_at_unixtime(&self, now_unixtime: u64) -> Option<u64> {
+        if self.relative_expiry && self.ttl_secs > 0 {
+            Some(now_unixtime.saturating_add(self.ttl_secs))
+
+        } else {
+            self.get_expires_at_unixtime()
+        }
+    }
+
+    /// Last access time, falls back to creation time
+    pub fn get_last_access_unixtime(&self) -> u64 {
+        self.last_access_unixtime.unwrap_or(self.created_unixtime)
+    }
+
+    /// Probabilistic early expiration (XFetch): item is due for refresh if
+    /// `now - compute_time * beta * ln(random) >= expires_at`, so probability rises as expiration approaches.
+    ///
+    /// - `random` - uniformly distributed in `(0, 1]`
+    ///
+    /// Items without compute time and items which never expire aren't refreshed early.
+    pub fn is_early_refresh_due(&self, now_unixtime: u64, beta: f64, random: f64) -> bool {
+        match (self.get_expires_at_unixtime(), self.compute_time_ms) {
+            (Some(expires_at), Some(compute_time_ms)) if compute_time_ms > 0 => {
+                let compute_time_secs = compute_time_ms as f64 / 1000.0;
+                now_unixtime as f64 - compute_time_secs * beta * random.ln() >= expires_at as f64
+            }
+            _ => false
+        }
+    }
 }
 
+/// Cache file postfix for default codec (JSON)
 pub const CACHE_FILENAME_POSTFIX: &str = "cache.json";
+
+/// Cache file postfix without extension, extension depends on codec
+pub const CACHE_FILENAME_STEM: &str = "cache";
 pub const METADATA_FILENAME_POSTFIX: &str = "cache-metadata.json";
 
+/// Directory for namespace lock files, inside instance directory
+pub const LOCKS_DIRNAME: &str = ".locks";
+
+enum CacheEntryState<T> {
+    Found {
+        value: T,
+        metadata: FileCacheItemMetadata,
+    },
+
+    /// Neither cache file nor metadata file exist
+    Missing,
+
+    Expired {
+        /// Item is kept to be read as stale
+        in_grace_period: bool,
+    },
+
+    /// One of companion files is missing
+    Orphaned,
+
+    Corrupted {
+        path: PathBuf,
+        reason: String,
+    },
+
+    /// Cache file is valid, but doesn't match requested type
+    TypeMismatch {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl<T> CacheEntryState<T> {
+    fn into_lookup(self) -> CacheLookup<(T, FileCacheItemMetadata)> {
+        match self {
+            CacheEntryState::Found { value, metadata } => CacheLookup::Found((value, metadata)),
+            CacheEntryState::Missing | CacheEntryState::Orphaned => CacheLookup::Missing,
+            CacheEntryState::Expired { .. } => CacheLookup::Expired,
+            CacheEntryState::Corrupted { path, reason } => CacheLookup::Corrupted { path, reason },
+            CacheEntryState::TypeMismatch { path, reason } => CacheLookup::TypeMismatch { path, reason },
+        }
+    }
+}
+
+/// Metadata, payload file path and payload (not decoded) of item
+type RawCacheEntry = (FileCacheItemMetadata, PathBuf, Vec<u8>);
+
 impl FileCacheService {
     /// Create instance of FileCacheService
     ///
@@ -47,10 +298,19 @@ impl FileCacheService {
     /// - `cache_instance_name` - name of current service, included in file hierarchy
     pub fn new(root_path: &NonBlankString,
                instance_name: &NonBlankString) -> OperationResult<FileCacheService> {
+        FileCacheService::create(Path::new(root_path.as_ref()), instance_name.as_ref())
+    }
+
+    /// Builder for service with non-default settings, see `FileCacheServiceBuilder`
+    pub fn builder(root_path: &NonBlankString, instance_name: &NonBlankString) -> FileCacheServiceBuilder {
+        FileCacheServiceBuilder::new(root_path, instance_name)
+    }
+
+    fn create(cache_root_path: &Path, instance_name: &str) -> OperationResult<FileCacheService> {
         info!("create file cache service, root path '{}', cache name '{}'",
-            root_path.as_ref(), instance_name.as_ref());
+            cache_root_path.display(), instance_name);
 
-        let cache_root_path = Path::new(root_path.as_ref());
+        let instance_dirname = encode_key(instance_name)?;
 
         if !cache_root_path.exists() {
             fs::create_dir_all(cache_root_path)?;
@@ -60,174 +320,1926 @@ impl FileCacheService {
 
         Ok(
             FileCacheService {
-                root_path: root_path.as_ref().to_string(),
-                instance_name: instance_name.as_ref().to_string(),
+                instance_name: instance_name.to_string(),
+                instance_path: cache_root_path.join(instance_dirname),
+                codec: CodecKind::default(),
+                compression: Compression::default(),
+                limits: CacheLimits::default(),
+                namespace_limits: HashMap::new(),
+                usage_estimates: UsageEstimates::default(),
+                policy_cache: PolicyCache::default(),
+                clock: Arc::new(SystemClock),
+                lock_timeout: None,
+                corruption_policy: CorruptionPolicy::default(),
+                quarantine_retention_secs: None,
+                default_expiry: Expiry::Never,
+                sliding_expiration: false,
+                grace_period_secs: None,
+                early_refresh_beta: None,
+                memory_tier: None,
+                single_flight: SingleFlight::default(),
+                #[cfg(feature = "tokio")]
+                async_single_flight: AsyncSingleFlight::default(),
+                cross_process_single_flight: false,
+                storage_format: StorageFormat::default(),
+                write_options: WriteOptions::default(),
             }
         )
     }
 
     /// Store `item` with cache `name` in `namespace`
     ///
-    /// - `ttl_secs` - cache time to live in seconds. `0` - immortal
-    pub fn store<'a>(&self, namespace: &NonBlankString, name: &NonBlankString, item: &impl Serialize,
-                     ttl_secs: u64) -> EmptyResult {
-        info!("store entity '{}' into file cache", name.as_ref());
-        let cache_item_path = self.get_cache_item_path(
-            &self.root_path, &self.instance_name, namespace.as_ref());
+    /// - `expiry` - `Expiry::Never`, `Expiry::After(Duration)` or `Expiry::At(SystemTime)`.
+    ///   `Duration` and `SystemTime` can be passed directly.
+    pub fn store(&self, namespace: &NonBlankString, name: &NonBlankString, item: &impl Serialize,
+                 expiry: impl Into<Expiry>) -> EmptyResult {
+        self.store_item(namespace.as_ref(), name.as_ref(), item, Some(expiry.into()), None)
+    }
 
-        if !cache_item_path.exists() {
-            fs::create_dir_all(&cache_item_path)?;
+    /// Store `item` with default expiry: namespace policy TTL (see `set_namespace_policy`),
+    /// then `FileCacheServiceBuilder::default_ttl`, never expires by default.
+    pub fn store_default(&self, namespace: &NonBlankString, name: &NonBlankString,
+                         item: &impl Serialize) -> EmptyResult {
+        self.store_item(namespace.as_ref(), name.as_ref(), item, None, None)
+    }
+
+    /// Get (retrieve) item from cache by `name` and `namespace`
+    ///
+    /// Expired (after grace period) and orphaned (without companion file) items are removed.
+    /// Corrupted items are handled according to `CorruptionPolicy`.
+    /// Use `get_detailed` to find out why item wasn't returned.
+    pub fn get<T: DeserializeOwned>(&self, namespace: &NonBlankString,
+                                    item_name: &NonBlankString) -> OptionalResult<T> {
+        self.get_item(namespace.as_ref(), item_name.as_ref())
+    }
+
+    /// Store `item` encoded with custom `codec`, for formats which aren't built in (see `CodecKind`).
+    ///
+    /// Item is marked with `CodecKind::Custom` and always stored in entry file (`StorageFormat::V2`).
+    /// It can be read only with `get_with_codec`, `get` reports it as type mismatch.
+    pub fn store_with_codec(&self, namespace: &NonBlankString, name: &NonBlankString, item: &impl Serialize,
+                            expiry: impl Into<Expiry>, codec: &impl Codec) -> EmptyResult {
+        self.store_encoded_item(namespace.as_ref(), name.as_ref(), Some(expiry.into()), |policy, expiry|
+            self.encode_payload(CodecKind::Custom, codec.encode(item)?, expiry, policy, None))
+    }
+
+    /// Get item stored with `store_with_codec`, decoded with `codec`.
+    ///
+    /// Items of built-in codecs are reported as type mismatch. Item which can't be decoded
+    /// with `codec` isn't removed, it could have been stored with another custom codec.
+    /// `CorruptionPolicy::Error` turns both cases into error.
+    pub fn get_with_codec<T: DeserializeOwned>(&self, namespace: &NonBlankString, item_name: &NonBlankString,
+                                               codec: &impl Codec) -> OptionalResult<T> {
+        let Some((payload, metadata)) = self.get_entry::<CustomPayload>(namespace.as_ref(), item_name.as_ref())? else {
+            return Ok(None)
+        };
+
+        let result = match metadata.codec {
+            CodecKind::Custom => codec.decode(&payload.0),
+            other => Err(FileCacheError::CodecError(format!("item has been stored with built-in codec {:?}", other)))
+        };
+
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.corruption_policy == CorruptionPolicy::Error => Err(e),
+            Err(e) => {
+                error!("couldn't decode cache item '{}' with custom codec: {}", item_name.as_ref(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Get item from cache like `get`, but report why it wasn't returned:
+    /// missing, expired, corrupted or type mismatch (see `CacheLookup`).
+    pub fn get_detailed<T: DeserializeOwned>(&self, namespace: &NonBlankString,
+                                             item_name: &NonBlankString) -> OperationResult<CacheLookup<T>> {
+        self.lookup_item(namespace.as_ref(), item_name.as_ref())
+    }
+
+    /// Remove item with cache `name` from `namespace`
+    ///
+    /// Both cache item and metadata files are removed.
+    /// Returns `true` if any of them existed.
+    pub fn remove(&self, namespace: &NonBlankString, name: &NonBlankString) -> OperationResult<bool> {
+        self.remove_item(namespace.as_ref(), name.as_ref())
+    }
+
+    /// Remove all items from `namespace`
+    pub fn invalidate_namespace(&self, namespace: &NonBlankString) -> EmptyResult {
+        self.clear_namespace(namespace.as_ref())
+    }
+
+    /// Remove all items of current cache instance (all namespaces)
+    pub fn invalidate_instance(&self) -> EmptyResult {
+        info!("invalidate file cache instance '{}'", self.instance_name);
+
+        let instance_path = &self.instance_path;
+
+        if instance_path.exists() {
+            for entry in fs::read_dir(instance_path)? {
+                let entry = entry?;
+                let entry_name = entry.file_name().to_string_lossy().to_string();
+
+                // Service directories are kept: processes could wait on namespace lock files right now
+                // (unused item lock files are removed below),
+                // quarantined items are kept for investigation, namespace policies aren't items.
+                if entry_name == LOCKS_DIRNAME || entry_name == QUARANTINE_DIRNAME || entry_name == POLICIES_DIRNAME {
+                    continue
+                }
+
+                if entry.file_type()?.is_dir() {
+                    let _lock = self.lock_namespace_exclusive(&entry_name)?;
+                    self.remove_dir_if_exists(&entry.path())?;
+
+                } else {
+                    self.remove_file_if_exists(&entry.path())?;
+                }
+            }
+        }
+
+        self.remove_all_unused_item_locks()?;
+
+        if let Some(memory_tier) = &self.memory_tier {
+            memory_tier.clear();
         }
 
+        info!("instance '{}' has been invalidated", self.instance_name);
+        Ok(())
+    }
+
+    /// - `expiry` - `None` for namespace policy or instance default expiry
+    /// - `compute_time_ms` - time it took to compute item, see `get_or_insert_with`
+    fn store_item(&self, namespace: &str, name: &str, item: &impl Serialize,
+                  expiry: Option<Expiry>, compute_time_ms: Option<u64>) -> EmptyResult {
+        self.store_encoded_item(namespace, name, expiry, |policy, expiry|
+            self.encode_item(item, expiry, policy, compute_time_ms))
+    }
+
+    /// - `encode` - returns cache file data and metadata of item for namespace policy and expiry
+    fn store_encoded_item(&self, namespace: &str, name: &str, expiry: Option<Expiry>,
+                          encode: impl FnOnce(&NamespacePolicy, Expiry) -> OperationResult<(Vec<u8>, FileCacheItemMetadata)>) -> EmptyResult {
+        let namespace_dirname = encode_key(namespace)?;
+        let policy = self.read_namespace_policy(&namespace_dirname)?.unwrap_or_default();
+        let expiry = expiry.or(policy.get_default_expiry()).unwrap_or(self.default_expiry);
+
+        info!("store entity '{}' into file cache", name);
+        let cache_item_path = self.get_cache_item_path(&namespace_dirname);
+
         debug!("cache item path '{}'", &cache_item_path.display());
 
-        let metadata_filename = self.get_filename(
-            name.as_ref(), METADATA_FILENAME_POSTFIX);
-        let metadata_file_path = self.get_cache_file_path(&cache_item_path,
-                                                          &metadata_filename);
+        let (data, item_metadata) = encode(&policy, expiry)?;
+
+        // Cache file name of v1 is derived from codec, which is unknown for custom one
+        let storage_format = match item_metadata.codec {
+            CodecKind::Custom => StorageFormat::V2,
+            _ => self.storage_format
+        };
+
+        let stored = {
+            let _lock = self.lock_namespace_exclusive(&namespace_dirname)?;
+
+            self.forget_in_memory(&cache_item_path, name)?;
+
+            if !cache_item_path.exists() {
+                fs::create_dir_all(&cache_item_path)?;
+            }
+
+            let replaced_bytes = self.get_stored_size(&cache_item_path, name)?;
+
+            let stored_bytes = match storage_format {
+                StorageFormat::V1 => self.write_v1_entry(&cache_item_path, name, &data, &item_metadata)?,
+                StorageFormat::V2 => self.write_v2_entry(&cache_item_path, name, &data, &item_metadata)?,
+            };
+
+            if self.write_options.fsync {
+                sync_dir(&cache_item_path)?;
+            }
+
+            StoredSize { bytes: stored_bytes, replaced_bytes }
+        };
+
+        info!("item '{}' has been saved into file cache", name);
+
+        self.enforce_limits(namespace, &namespace_dirname, name, &stored, &policy)?;
+
+        Ok(())
+    }
+
+    /// Write cache file and metadata file (`StorageFormat::V1`), returns size of written files.
+    /// Caller must hold exclusive namespace lock.
+    fn write_v1_entry(&self, cache_item_path: &Path, name: &str, data: &[u8],
+                      metadata: &FileCacheItemMetadata) -> OperationResult<u64> {
+        let metadata_file_path = self.get_metadata_file_path(cache_item_path, name)?;
         debug!("destination metadata file path '{}'", &metadata_file_path.display());
+
+        let file_path = self.get_codec_file_path(cache_item_path, name, metadata.codec)?;
+        debug!("destination file path '{}'", &file_path.display());
+
+        let metadata_json = serde_json::to_string(metadata)?;
+
+        // Entry file takes precedence over v1 files, so it goes away first
+        self.remove_file_if_exists(&self.get_entry_file_path(cache_item_path, name)?)?;
+
+        // Both files are fully written and flushed before any rename happens.
+        // Cache file goes first: crash between renames leaves complete cache file
+        // with previous metadata, or cache file without metadata (`get` cleans it up).
+        let temp_file = write_temp_file(cache_item_path, data, &self.write_options)?;
+        let metadata_temp_file = write_temp_file(cache_item_path, metadata_json.as_bytes(), &self.write_options)?;
+
+        persist_temp_file(temp_file, &file_path)?;
+        persist_temp_file(metadata_temp_file, &metadata_file_path)?;
+
+        // Item could be stored with another codec before
+        for previous_codec in CodecKind::all().iter().filter(|previous_codec| **previous_codec != metadata.codec) {
+            let previous_file_path = self.get_codec_file_path(cache_item_path, name, *previous_codec)?;
+            self.remove_file_if_exists(&previous_file_path)?;
+        }
+
+        Ok((data.len() + metadata_json.len()) as u64)
+    }
+
+    /// Write entry file (`StorageFormat::V2`), v1 files of item are removed. Returns size of entry file.
+    /// Caller must hold exclusive namespace lock.
+    fn write_v2_entry(&self, cache_item_path: &Path, name: &str, data: &[u8],
+                      metadata: &FileCacheItemMetadata) -> OperationResult<u64> {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+        debug!("destination entry file path '{}'", &entry_file_path.display());
+
+        let entry = encode_entry(metadata, data);
+        let temp_file = write_temp_file(cache_item_path, &entry, &self.write_options)?;
+        persist_temp_file(temp_file, &entry_file_path)?;
+
+        // Item could be stored with format v1 before. Leftovers of crash are harmless: entry file takes precedence.
+        self.remove_file_if_exists(&self.get_metadata_file_path(cache_item_path, name)?)?;
+
+        for codec in CodecKind::all() {
+            self.remove_file_if_exists(&self.get_codec_file_path(cache_item_path, name, *codec)?)?;
+        }
+
+        Ok(entry.len() as u64)
+    }
+
+    /// Cache file data and metadata of new item
+    fn encode_item(&self, item: &impl Serialize, expiry: Expiry, policy: &NamespacePolicy,
+                   compute_time_ms: Option<u64>) -> OperationResult<(Vec<u8>, FileCacheItemMetadata)> {
+        let codec = policy.codec.unwrap_or(self.codec);
+        self.encode_payload(codec, codec.encode(item)?, expiry, policy, compute_time_ms)
+    }
+
+    /// Cache file data and metadata of new item, encoded with `codec`
+    fn encode_payload(&self, codec: CodecKind, payload: Vec<u8>, expiry: Expiry, policy: &NamespacePolicy,
+                      compute_time_ms: Option<u64>) -> OperationResult<(Vec<u8>, FileCacheItemMetadata)> {
+        let compression = policy.compression.unwrap_or(self.compression);
+
         let now_unixtime = self.get_now_in_unixtime_secs()?;
+        let expires_at = expiry.get_expires_at_unixtime(now_unixtime);
         let item_metadata: FileCacheItemMetadata = FileCacheItemMetadata {
-            ttl_secs,
+            ttl_secs: get_legacy_ttl_secs(expires_at, now_unixtime),
             created_unixtime: now_unixtime,
+            expires_at,
+            relative_expiry: matches!(expiry, Expiry::After(_)),
+            codec,
+            compression,
+            last_access_unixtime: None,
+            grace_period_secs: policy.grace_period_secs.or(self.grace_period_secs).filter(|secs| *secs > 0),
+            compute_time_ms,
         };
-        let metadata_json = serde_json::to_string(&item_metadata)?;
-        fs::write(&metadata_file_path, metadata_json)?;
-        info!("cache item metadata has been created");
 
-        let filename = self.get_filename(name.as_ref(), CACHE_FILENAME_POSTFIX);
-        let file_path = self.get_cache_file_path(&cache_item_path, &filename);
-        debug!("destination file path '{}'", &file_path.display());
+        let data = compression.compress(payload)?;
+
+        Ok((data, item_metadata))
+    }
 
-        let json = serde_json::to_string(item)?;
+    fn get_item<T: DeserializeOwned>(&self, namespace: &str, item_name: &str) -> OptionalResult<T> {
+        Ok(self.get_entry(namespace, item_name)?.map(|(value, _)| value))
+    }
 
-        if file_path.exists() {
-            fs::remove_file(&file_path)?;
-        }
+    /// Item with its metadata, corrupted items are reported according to `CorruptionPolicy`
+    fn get_entry<T: DeserializeOwned>(&self, namespace: &str,
+                                      item_name: &str) -> OptionalResult<(T, FileCacheItemMetadata)> {
+        let lookup = self.lookup_entry(namespace, item_name)?;
 
-        fs::write(&file_path, json)?;
+        match self.corruption_policy {
+            CorruptionPolicy::Error => lookup.into_result(),
+            _ => Ok(lookup.found())
+        }
+    }
 
-        info!("item '{}' has been saved into file cache", name.as_ref());
-        Ok(())
+    fn lookup_item<T: DeserializeOwned>(&self, namespace: &str, item_name: &str) -> OperationResult<CacheLookup<T>> {
+        Ok(self.lookup_entry(namespace, item_name)?.map(|(value, _)| value))
     }
 
-    /// Get (retrieve) item from cache by `name` and `namespace`
-    pub fn get<'de, T: DeserializeOwned>(&self, namespace: &NonBlankString,
-                                         item_name: &NonBlankString) -> OptionalResult<T> {
-        info!("get entity from file cache: namespace='{}', item_name='{}'", namespace.as_ref(), item_name.as_ref());
+    fn lookup_entry<T: DeserializeOwned>(&self, namespace: &str,
+                                         item_name: &str) -> OperationResult<CacheLookup<(T, FileCacheItemMetadata)>> {
+        info!("get entity from file cache: namespace='{}', item_name='{}'", namespace, item_name);
 
-        let cache_item_path = self.get_cache_item_path(
-            &self.root_path, &self.instance_name, namespace.as_ref());
+        let namespace_dirname = encode_key(namespace)?;
+        let cache_item_path = self.get_cache_item_path(&namespace_dirname);
 
-        let metadata_filename = self.get_filename(
-            item_name.as_ref(), METADATA_FILENAME_POSTFIX);
-        let metadata_file_path = self.get_cache_file_path(&cache_item_path,
-                                                          &metadata_filename);
-        debug!("destination metadata file path '{}'", &metadata_file_path.display());
+        if !cache_item_path.exists() {
+            info!("file cache entity '{}' wasn't found", item_name);
+            return Ok(CacheLookup::Missing)
+        }
+
+        let (state, access_update) = {
+            let _lock = self.lock_namespace_shared(&namespace_dirname)?;
 
-        let filename = self.get_filename(item_name.as_ref(), CACHE_FILENAME_POSTFIX);
-        let file_path = self.get_cache_file_path(&cache_item_path, &filename);
+            let state = self.read_cache_entry::<T>(&cache_item_path, item_name)?;
 
-        if metadata_file_path.exists() {
-            let metadata_json = fs::read_to_string(&metadata_file_path)?;
+            let access_update = match &state {
+                CacheEntryState::Found { metadata, .. } =>
+                    self.get_access_update(&namespace_dirname, &cache_item_path, item_name, metadata)?,
+                _ => None
+            };
 
-            match serde_json::from_str::<FileCacheItemMetadata>(&metadata_json) {
-                Ok(metadata) => {
-                    let now_unixtime = self.get_now_in_unixtime_secs()?;
+            (state, access_update)
+        };
 
-                    if now_unixtime > metadata.created_unixtime {
-                        let diff_secs = now_unixtime - metadata.created_unixtime;
+        // Metadata isn't rewritten under shared lock, other readers could read it meanwhile
+        let state = match (state, access_update) {
+            (CacheEntryState::Found { value, metadata }, Some(update)) => {
+                let metadata = self.update_on_access(&namespace_dirname, &cache_item_path, item_name, metadata, update)?;
+                CacheEntryState::Found { value, metadata }
+            }
+            (state, _) => state
+        };
 
-                        if metadata.ttl_secs > 0 && (diff_secs > metadata.ttl_secs) {
-                            info!("cache item '{}' has been expired and will be removed", item_name.as_ref());
+        match state {
+            CacheEntryState::Found { .. } => {
+                info!("entity '{}' has been loaded from file cache", item_name);
+                Ok(state.into_lookup())
+            }
+            CacheEntryState::Missing => {
+                info!("file cache entity '{}' wasn't found", item_name);
+                Ok(state.into_lookup())
+            }
+            _ => {
+                // Shared lock can't be upgraded atomically, so entry state is checked again
+                // under exclusive lock: another process could rewrite entry in between.
+                let _lock = self.lock_namespace_exclusive(&namespace_dirname)?;
 
-                            if file_path.exists() {
-                                fs::remove_file(file_path)?;
-                                fs::remove_file(metadata_file_path)?;
-                            }
+                let state = self.read_cache_entry::<T>(&cache_item_path, item_name)?;
 
-                            return Ok(None);
-                        }
+                match &state {
+                    CacheEntryState::Found { .. } => {
+                        info!("entity '{}' has been loaded from file cache", item_name);
                     }
-
-                    if file_path.exists() {
-                        let json = fs::read_to_string(&file_path)?;
-
-                        match serde_json::from_str::<T>(&json) {
-                            Ok(value) => {
-                                info!("entity '{}' has been loaded from file cache", item_name.as_ref());
-                                Ok(Some(value))
-                            }
-                            Err(e) => {
-                                error!("couldn't deserialize cache item: {}", e);
-                                fs::remove_file(&file_path)?;
-                                fs::remove_file(&metadata_file_path)?;
-                                Ok(None)
-                            }
-                        }
-                    } else {
-                        info!("file cache entity '{}' wasn't found", item_name.as_ref());
-                        Ok(None)
+                    CacheEntryState::Missing => {}
+                    CacheEntryState::Expired { in_grace_period: true } => {
+                        info!("cache item '{}' has been expired, kept for grace period", item_name);
+                    }
+                    CacheEntryState::Expired { in_grace_period: false } => {
+                        info!("cache item '{}' has been expired and will be removed", item_name);
+                        self.remove_cache_entry_files(&cache_item_path, item_name)?;
+                    }
+                    CacheEntryState::Orphaned => {
+                        info!("companion file not found for item '{}', cache files will be removed", item_name);
+                        self.remove_cache_entry_files(&cache_item_path, item_name)?;
                     }
-                },
-                Err(e) => {
-                    error!("corrupted metadata file: {}", e);
-                    if file_path.exists() {
-                        fs::remove_file(&metadata_file_path)?;
-                        fs::remove_file(&file_path)?;
+                    CacheEntryState::Corrupted { reason, .. } => {
+                        error!("cache item '{}' is corrupted: {}", item_name, reason);
+                        self.discard_corrupted_entry(&namespace_dirname, item_name, reason)?;
+                    }
+                    CacheEntryState::TypeMismatch { reason, .. } => {
+                        error!("cache item '{}' doesn't match requested type: {}", item_name, reason);
+                        self.discard_corrupted_entry(&namespace_dirname, item_name, reason)?;
                     }
-                    Ok(None)
                 }
+
+                Ok(state.into_lookup())
             }
+        }
+    }
+
+    fn remove_item(&self, namespace: &str, name: &str) -> OperationResult<bool> {
+        info!("remove entity from file cache: namespace='{}', item_name='{}'", namespace, name);
+
+        let namespace_dirname = encode_key(namespace)?;
+        let cache_item_path = self.get_cache_item_path(&namespace_dirname);
+
+        if !cache_item_path.exists() {
+            info!("file cache entity '{}' wasn't found", name);
+            return Ok(false)
+        }
+
+        let _lock = self.lock_namespace_exclusive(&namespace_dirname)?;
+
+        if self.remove_cache_entry_files(&cache_item_path, name)? {
+            info!("item '{}' has been removed from file cache", name);
+            Ok(true)
 
         } else {
-            info!("metadata file not found for item '{}', cache file will be removed", item_name.as_ref());
-            if file_path.exists() {
-                fs::remove_file(file_path)?;
-            }
-            Ok(None)
+            info!("file cache entity '{}' wasn't found", name);
+            Ok(false)
         }
     }
 
-    fn get_cache_item_path(&self, root_path: &str, instance_name: &str, namespace: &str) -> PathBuf {
-        Path::new(&root_path).join(&instance_name).join(&namespace)
+    fn clear_namespace(&self, namespace: &str) -> EmptyResult {
+        info!("invalidate file cache namespace '{}'", namespace);
+
+        let namespace_dirname = encode_key(namespace)?;
+        let namespace_path = self.get_cache_item_path(&namespace_dirname);
+
+        if namespace_path.exists() {
+            let _lock = self.lock_namespace_exclusive(&namespace_dirname)?;
+            self.remove_dir_if_exists(&namespace_path)?;
+        }
+
+        self.remove_unused_item_locks(&namespace_dirname)?;
+
+        if let Some(memory_tier) = &self.memory_tier {
+            memory_tier.remove_dir(&namespace_path);
+        }
+
+        info!("namespace '{}' has been invalidated", namespace);
+        Ok(())
     }
 
-    fn get_filename(&self, cache_item_name: &str, postfix: &str) -> String {
-        format!("{}-{}", cache_item_name, postfix)
+    /// Read cache entry files, or take them from memory tier if file with metadata hasn't been replaced.
+    /// Caller must hold namespace lock.
+    fn read_cache_entry<T: DeserializeOwned>(&self, cache_item_path: &Path,
+                                             name: &str) -> OperationResult<CacheEntryState<T>> {
+        // Stamp is taken before files are read: concurrent write makes memory entry outdated, not vice versa
+        let stamp = match &self.memory_tier {
+            Some(_) => self.read_metadata_stamp(cache_item_path, name)?,
+            None => None
+        };
+
+        let memory_entry = match (&self.memory_tier, &stamp) {
+            (Some(memory_tier), Some((stamp_path, stamp))) => memory_tier.get(stamp_path, stamp),
+            _ => None
+        };
+
+        let (metadata, file_path, data, from_memory) = match memory_entry {
+            Some((metadata, data)) => {
+                debug!("item '{}' has been found in memory tier", name);
+                let file_path = self.get_payload_file_path(cache_item_path, name, &metadata)?;
+                (metadata, file_path, data, true)
+            }
+            None => match self.read_entry_files(cache_item_path, name)? {
+                Ok((metadata, file_path, data)) => (metadata, file_path, data, false),
+                Err(state) => return Ok(state)
+            }
+        };
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        if metadata.is_expired(now_unixtime) {
+            return Ok(CacheEntryState::Expired { in_grace_period: !metadata.is_grace_period_over(now_unixtime) })
+        }
+
+        match (&self.memory_tier, stamp) {
+            (Some(memory_tier), Some((stamp_path, stamp))) if !from_memory => {
+                let state = decode_cache_data(file_path, data.clone(), metadata);
+
+                if let CacheEntryState::Found { metadata, .. } = &state {
+                    memory_tier.insert(stamp_path, stamp, metadata.clone(), data);
+                }
+
+                Ok(state)
+            }
+            _ => Ok(decode_cache_data(file_path, data, metadata))
+        }
     }
 
-    fn get_cache_file_path(&self, cache_item_path: &PathBuf, cache_item_name: &str) -> PathBuf {
-        cache_item_path.join(cache_item_name)
+    /// Metadata and payload from entry file (format v2), or from metadata file and cache file (v1).
+    /// Payload isn't decoded, expired v1 item is reported without reading cache file.
+    ///
+    /// Returns state of entry which can't be read. Caller must hold namespace lock.
+    fn read_entry_files<T>(&self, cache_item_path: &Path,
+                           name: &str) -> OperationResult<Result<RawCacheEntry, CacheEntryState<T>>> {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+
+        if let Some(data) = self.read_file_if_exists(&entry_file_path)? {
+            return match decode_entry(data) {
+                Ok((metadata, payload)) => Ok(Ok((metadata, entry_file_path, payload))),
+                Err(reason) => {
+                    error!("corrupted entry file '{}': {}", entry_file_path.display(), reason);
+                    Ok(Err(CacheEntryState::Corrupted { path: entry_file_path, reason }))
+                }
+            }
+        }
+
+        let metadata = match self.read_v1_metadata(cache_item_path, name) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => {
+                for codec in CodecKind::all() {
+                    if self.get_codec_file_path(cache_item_path, name, *codec)?.exists() {
+                        return Ok(Err(CacheEntryState::Orphaned))
+                    }
+                }
+
+                return Ok(Err(CacheEntryState::Missing))
+            }
+            Err(FileCacheError::Corrupted { path, reason }) => {
+                error!("corrupted metadata file '{}': {}", path.display(), reason);
+                return Ok(Err(CacheEntryState::Corrupted { path, reason }))
+            }
+            Err(e) => return Err(e)
+        };
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        if metadata.is_expired(now_unixtime) {
+            return Ok(Err(CacheEntryState::Expired { in_grace_period: !metadata.is_grace_period_over(now_unixtime) }))
+        }
+
+        let file_path = self.get_codec_file_path(cache_item_path, name, metadata.codec)?;
+
+        match self.read_file_if_exists(&file_path)? {
+            Some(data) => Ok(Ok((metadata, file_path, data))),
+            None => Ok(Err(CacheEntryState::Orphaned))
+        }
     }
 
-    fn get_now_in_unixtime_secs(&self) -> OperationResult<u64> {
-        match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(tm) => Ok(tm.as_secs()),
-            Err(e) => {
-                error!("{}", e);
-                Err(FileCacheError::Default)
+    /// Read and decode payload of item described by `metadata`, expiration isn't checked.
+    /// Caller must hold namespace lock.
+    fn read_cache_file<T: DeserializeOwned>(&self, cache_item_path: &Path, name: &str,
+                                            metadata: FileCacheItemMetadata) -> OperationResult<CacheEntryState<T>> {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+
+        if let Some(data) = self.read_file_if_exists(&entry_file_path)? {
+            return match decode_entry(data) {
+                Ok((_, payload)) => Ok(decode_cache_data(entry_file_path, payload, metadata)),
+                Err(reason) => {
+                    error!("corrupted entry file '{}': {}", entry_file_path.display(), reason);
+                    Ok(CacheEntryState::Corrupted { path: entry_file_path, reason })
+                }
             }
         }
+
+        let file_path = self.get_codec_file_path(cache_item_path, name, metadata.codec)?;
+
+        match self.read_file_if_exists(&file_path)? {
+            Some(data) => Ok(decode_cache_data(file_path, data, metadata)),
+            None => Ok(CacheEntryState::Orphaned)
+        }
+    }
+
+    /// Read item metadata: entry file header (format v2) takes precedence over metadata file (v1).
+    /// Returns `FileCacheError::Corrupted` for corrupted metadata.
+    fn read_metadata(&self, cache_item_path: &Path, name: &str) -> OptionalResult<FileCacheItemMetadata> {
+        match self.read_entry_header(cache_item_path, name)? {
+            Some(metadata) => Ok(Some(metadata)),
+            None => self.read_v1_metadata(cache_item_path, name)
+        }
+    }
+
+    /// Read metadata from entry file header, payload isn't read: only header checksum is verified,
+    /// payload checksum is verified by `get`. Returns `FileCacheError::Corrupted` for invalid header.
+    fn read_entry_header(&self, cache_item_path: &Path, name: &str) -> OptionalResult<FileCacheItemMetadata> {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+
+        let file = match File::open(&entry_file_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(FileCacheError::from(e))
+        };
+
+        let mut header = Vec::with_capacity(ENTRY_HEADER_LEN);
+        file.take(ENTRY_HEADER_LEN as u64).read_to_end(&mut header)?;
+
+        match decode_entry_header(&header) {
+            Ok((metadata, _)) => Ok(Some(metadata)),
+            Err(reason) => Err(FileCacheError::Corrupted { path: entry_file_path, reason })
+        }
+    }
+
+    /// Read item metadata file (format v1). Returns `FileCacheError::Corrupted` for corrupted metadata.
+    fn read_v1_metadata(&self, cache_item_path: &Path, name: &str) -> OptionalResult<FileCacheItemMetadata> {
+        let metadata_file_path = self.get_metadata_file_path(cache_item_path, name)?;
+        debug!("metadata file path '{}'", &metadata_file_path.display());
+
+        match self.read_file_if_exists(&metadata_file_path)? {
+            Some(metadata_json) => Ok(Some(parse_metadata(metadata_file_path, &metadata_json)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Rewrite item metadata atomically: metadata file, or entry file with the same payload (format v2).
+    /// Memory tier entry is kept up to date.
+    fn write_metadata(&self, cache_item_path: &Path, name: &str,
+                      metadata: &FileCacheItemMetadata) -> EmptyResult {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+
+        let (metadata_file_path, content) = match self.read_file_if_exists(&entry_file_path)? {
+            Some(data) => {
+                let (_, payload) = decode_entry(data)
+                    .map_err(|reason| FileCacheError::Corrupted { path: entry_file_path.clone(), reason })?;
+                (entry_file_path, encode_entry(metadata, &payload))
+            }
+            None => (self.get_metadata_file_path(cache_item_path, name)?, serde_json::to_vec(metadata)?)
+        };
+
+        write_file_atomically(&metadata_file_path, &content, &self.write_options)?;
+
+        if let Some(memory_tier) = &self.memory_tier {
+            match FileStamp::read(&metadata_file_path)? {
+                Some(stamp) => memory_tier.update_metadata(&metadata_file_path, stamp, metadata.clone()),
+                None => memory_tier.remove(&metadata_file_path)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stamp of file with item metadata for memory tier: entry file (format v2) or metadata file (v1).
+    /// `None` if item doesn't exist.
+    fn read_metadata_stamp(&self, cache_item_path: &Path, name: &str) -> OptionalResult<(PathBuf, FileStamp)> {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+
+        if let Some(stamp) = FileStamp::read(&entry_file_path)? {
+            return Ok(Some((entry_file_path, stamp)))
+        }
+
+        let metadata_file_path = self.get_metadata_file_path(cache_item_path, name)?;
+        Ok(FileStamp::read(&metadata_file_path)?.map(|stamp| (metadata_file_path, stamp)))
+    }
+
+    /// Drop memory tier entries of item, e.g. when item is rewritten or removed
+    fn forget_in_memory(&self, cache_item_path: &Path, name: &str) -> EmptyResult {
+        if let Some(memory_tier) = &self.memory_tier {
+            memory_tier.remove(&self.get_entry_file_path(cache_item_path, name)?);
+            memory_tier.remove(&self.get_metadata_file_path(cache_item_path, name)?);
+        }
+
+        Ok(())
+    }
+
+    /// Remove entry file, metadata file and cache files of any codec. Caller must hold exclusive namespace lock.
+    ///
+    /// Returns `true` if any file existed.
+    fn remove_cache_entry_files(&self, cache_item_path: &Path, name: &str) -> OperationResult<bool> {
+        self.forget_in_memory(cache_item_path, name)?;
+
+        let mut removed = self.remove_file_if_exists(&self.get_entry_file_path(cache_item_path, name)?)?;
+        removed |= self.remove_file_if_exists(&self.get_metadata_file_path(cache_item_path, name)?)?;
+
+        for codec in CodecKind::all() {
+            let file_path = self.get_codec_file_path(cache_item_path, name, *codec)?;
+            removed |= self.remove_file_if_exists(&file_path)?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Payload size in bytes: entry file without header (format v2) or cache file (v1).
+    /// `None` if there's no payload.
+    fn get_payload_size(&self, cache_item_path: &Path, name: &str, codec: CodecKind) -> OptionalResult<u64> {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+
+        if entry_file_path.exists() {
+            return Ok(Some(get_file_size(&entry_file_path)?.saturating_sub(ENTRY_HEADER_LEN as u64)))
+        }
+
+        let file_path = self.get_codec_file_path(cache_item_path, name, codec)?;
+
+        if file_path.exists() {
+            Ok(Some(get_file_size(&file_path)?))
+
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// File with payload of item: entry file (format v2) or cache file (v1)
+    fn get_payload_file_path(&self, cache_item_path: &Path, name: &str,
+                             metadata: &FileCacheItemMetadata) -> OperationResult<PathBuf> {
+        let entry_file_path = self.get_entry_file_path(cache_item_path, name)?;
+
+        if entry_file_path.exists() {
+            Ok(entry_file_path)
+
+        } else {
+            self.get_codec_file_path(cache_item_path, name, metadata.codec)
+        }
+    }
+
+    fn lock_namespace_shared(&self, namespace_dirname: &str) -> OperationResult<FileLock> {
+        let lock_file_path = self.get_lock_file_path(namespace_dirname)?;
+        FileLock::shared(&lock_file_path, self.lock_timeout)
+    }
+
+    fn lock_namespace_exclusive(&self, namespace_dirname: &str) -> OperationResult<FileLock> {
+        let lock_file_path = self.get_lock_file_path(namespace_dirname)?;
+        FileLock::exclusive(&lock_file_path, self.lock_timeout)
+    }
+
+    /// Exclusive namespace lock if available right away, `None` if namespace is locked
+    fn try_lock_namespace_exclusive(&self, namespace_dirname: &str) -> OptionalResult<FileLock> {
+        let lock_file_path = self.get_lock_file_path(namespace_dirname)?;
+        FileLock::try_exclusive(&lock_file_path)
+    }
+
+    /// Lock files live outside of namespace directories, so namespace invalidation
+    /// doesn't remove lock file which other processes could wait on.
+    fn get_lock_file_path(&self, namespace_dirname: &str) -> OperationResult<PathBuf> {
+        let locks_path = self.get_locks_path();
+
+        if !locks_path.exists() {
+            fs::create_dir_all(&locks_path)?;
+        }
+
+        Ok(self.get_namespace_lock_file_path(namespace_dirname))
+    }
+
+    fn get_locks_path(&self) -> PathBuf {
+        self.instance_path.join(LOCKS_DIRNAME)
+    }
+
+    /// Lock file path, locks directory isn't created
+    fn get_namespace_lock_file_path(&self, namespace_dirname: &str) -> PathBuf {
+        self.get_locks_path().join(format!("{}.{}", namespace_dirname, LOCK_FILENAME_POSTFIX))
+    }
+
+    fn get_cache_item_path(&self, namespace_dirname: &str) -> PathBuf {
+        self.instance_path.join(namespace_dirname)
+    }
+
+    fn get_filename(&self, cache_item_name: &str, postfix: &str) -> OperationResult<String> {
+        Ok(format!("{}-{}", encode_key(cache_item_name)?, postfix))
+    }
+
+    /// Metadata file path: `[NAMESPACE PATH]/[ITEM-NAME]-cache-metadata.json`
+    fn get_metadata_file_path(&self, cache_item_path: &Path, name: &str) -> OperationResult<PathBuf> {
+        let filename = self.get_filename(name, METADATA_FILENAME_POSTFIX)?;
+        Ok(self.get_cache_file_path(cache_item_path, &filename))
+    }
+
+    /// Entry file path (format v2): `[NAMESPACE PATH]/[ITEM-NAME]-entry.fkesh`
+    fn get_entry_file_path(&self, cache_item_path: &Path, name: &str) -> OperationResult<PathBuf> {
+        let filename = self.get_filename(name, ENTRY_FILENAME_POSTFIX)?;
+        Ok(self.get_cache_file_path(cache_item_path, &filename))
+    }
+
+    /// Cache file path: `[NAMESPACE PATH]/[ITEM-NAME]-cache.[CODEC EXTENSION]`
+    fn get_codec_file_path(&self, cache_item_path: &Path, name: &str,
+                           codec: CodecKind) -> OperationResult<PathBuf> {
+        let postfix = format!("{}.{}", CACHE_FILENAME_STEM, codec.extension());
+        let filename = self.get_filename(name, &postfix)?;
+        Ok(self.get_cache_file_path(cache_item_path, &filename))
+    }
+
+    fn get_cache_file_path(&self, cache_item_path: &Path, cache_item_name: &str) -> PathBuf {
+        cache_item_path.join(cache_item_name)
+    }
+
+    fn remove_file_if_exists(&self, file_path: &Path) -> OperationResult<bool> {
+        match fs::remove_file(file_path) {
+            Ok(_) => {
+                debug!("file '{}' has been removed", file_path.display());
+                Ok(true)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(FileCacheError::from(e))
+        }
+    }
+
+    fn read_file_if_exists(&self, file_path: &Path) -> OptionalResult<Vec<u8>> {
+        match fs::read(file_path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(FileCacheError::from(e))
+        }
+    }
+
+    fn remove_dir_if_exists(&self, dir_path: &Path) -> OperationResult<bool> {
+        match fs::remove_dir_all(dir_path) {
+            Ok(_) => {
+                debug!("directory '{}' has been removed", dir_path.display());
+                Ok(true)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(FileCacheError::from(e))
+        }
+    }
+
+    fn get_now_in_unixtime_secs(&self) -> OperationResult<u64> {
+        match self.clock.now().duration_since(UNIX_EPOCH) {
+            Ok(tm) => Ok(tm.as_secs()),
+            Err(e) => {
+                error!("{}", e);
+                Err(FileCacheError::ClockError(e))
+            }
+        }
+    }
+}
+
+/// `ttl_secs` metadata field: readers without `expires_at` support still get (approximate) TTL.
+/// Already expired items get `1`: `0` means "never expires" for such readers.
+pub(crate) fn get_legacy_ttl_secs(expires_at: Option<u64>, now_unixtime: u64) -> u64 {
+    expires_at.map_or(0, |expires_at| expires_at.saturating_sub(now_unixtime).max(1))
+}
+
+/// Decompress and decode cache file `data`
+fn decode_cache_data<T: DeserializeOwned>(file_path: PathBuf, data: Vec<u8>,
+                                          metadata: FileCacheItemMetadata) -> CacheEntryState<T> {
+    let data = match metadata.compression.decompress(data) {
+        Ok(data) => data,
+        Err(e) => {
+            error!("couldn't decompress cache item: {}", e);
+            return CacheEntryState::Corrupted { path: file_path, reason: e.to_string() }
+        }
+    };
+
+    match metadata.codec.decode::<T>(&data) {
+        Ok(value) => CacheEntryState::Found { value, metadata },
+        Err(e) => {
+            error!("couldn't deserialize cache item: {}", e);
+
+            // Well-formed data of another shape, e.g. cached struct has been changed
+            if metadata.codec.decode::<IgnoredAny>(&data).is_ok() {
+                CacheEntryState::TypeMismatch { path: file_path, reason: e.to_string() }
+
+            } else {
+                CacheEntryState::Corrupted { path: file_path, reason: e.to_string() }
+            }
+        }
+    }
+}
+
+/// Returns `FileCacheError::Corrupted` for invalid metadata
+fn parse_metadata(metadata_file_path: PathBuf, metadata_json: &[u8]) -> OperationResult<FileCacheItemMetadata> {
+    serde_json::from_slice(metadata_json)
+        .map_err(|e| FileCacheError::Corrupted { path: metadata_file_path, reason: e.to_string() })
+}
+
+#[cfg(test)]
+mod ttl_tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::expiry::Expiry;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheItemMetadata, FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    #[test]
+    fn delete_all_cache_item_file_if_metadata_is_missing() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(1000)).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        let metadata_file = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref())
+            .join(metadata_filename);
+
+        fs::remove_file(metadata_file).unwrap();
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+
+        let cache_item_filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let cache_item_file = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref())
+            .join(cache_item_filename);
+
+        assert!(!cache_item_file.exists());
+    }
+
+    #[test]
+    fn return_none_if_metadata_companion_file_is_missing() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(1000)).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        let metadata_file = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref())
+            .join(metadata_filename);
+
+        fs::remove_file(metadata_file).unwrap();
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn return_item_with_existing_ttl() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(1000)).is_ok());
+
+        clock.advance(Duration::from_secs(1));
+
+        let result = service.get::<Demo>(&namespace, &name).unwrap().unwrap();
+
+        assert_eq!(result, demo);
+    }
+
+    #[test]
+    fn return_none_for_item_with_expired_ttl() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(1)).is_ok());
+
+        clock.advance(Duration::from_secs(3));
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn legacy_ttl_should_not_be_zero_for_expired_items() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        let expiries = [
+            Expiry::After(Duration::ZERO),
+            Expiry::At(SystemTime::now() - Duration::from_secs(3600)),
+        ];
+
+        for expiry in expiries {
+            let name = get_random_nonblank_string();
+
+            assert!(service.store(&namespace, &name, &get_demo_entity(), expiry).is_ok());
+
+            let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+            let metadata_file_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(metadata_filename);
+            let metadata: FileCacheItemMetadata = serde_json::from_slice(&fs::read(metadata_file_path).unwrap()).unwrap();
+
+            assert_eq!(metadata.ttl_secs, 1);
+            assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn remove_files_for_cache_item_with_expired_ttl() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(1)).is_ok());
+
+        clock.advance(Duration::from_secs(3));
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        let metadata_file = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref())
+            .join(metadata_filename);
+
+        assert!(!metadata_file.exists());
+
+        let cache_item_filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let cache_item_file = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref())
+            .join(cache_item_filename);
+
+        assert!(!cache_item_file.exists());
+    }
+
+    #[test]
+    fn item_should_be_retrieved_with_zero_ttl() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        clock.advance(Duration::from_secs(1));
+
+        let result = service.get::<Demo>(&namespace, &name).unwrap().unwrap();
+
+        assert_eq!(result, demo);
+    }
+}
+
+#[cfg(test)]
+mod memory_tier_tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::expiry::Expiry;
+    use crate::limits::CacheLimits;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    const MEMORY_LIMITS: CacheLimits = CacheLimits { max_total_bytes: None, max_entries: Some(100) };
+
+    #[test]
+    fn item_should_be_read_from_memory() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .memory_tier(MEMORY_LIMITS)
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+        assert_eq!(service.get::<Demo>(&namespace, &name).unwrap().unwrap(), demo);
+
+        // Cache file isn't read while metadata file is the same
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let file_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(filename);
+        fs::write(&file_path, "{").unwrap();
+
+        assert_eq!(service.get::<Demo>(&namespace, &name).unwrap(), Some(demo));
+
+        assert!(service.remove(&namespace, &name).unwrap());
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn item_stored_by_another_process_should_be_read() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .memory_tier(MEMORY_LIMITS)
+            .build().unwrap();
+
+        // Doesn't share memory tier, as service of another process
+        let other_service = FileCacheService::builder(&root_path_str, &instance_name)
+            .memory_tier(MEMORY_LIMITS)
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        let demo = get_demo_entity();
+        assert!(other_service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        assert_eq!(service.get::<Demo>(&namespace, &name).unwrap(), Some(demo));
+
+        assert!(other_service.remove(&namespace, &name).unwrap());
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn expired_item_should_not_be_returned_from_memory() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .memory_tier(MEMORY_LIMITS)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        clock.advance(Duration::from_secs(61));
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+}
+
+#[cfg(test)]
+mod storage_format_tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::expiry::Expiry;
+    use crate::format::{StorageFormat, ENTRY_FILENAME_POSTFIX};
+    use crate::limits::CacheLimits;
+    use crate::lookup::CacheLookup;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    fn get_filenames(namespace_path: &Path) -> Vec<String> {
+        let mut filenames: Vec<String> = fs::read_dir(namespace_path).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+
+        filenames.sort();
+        filenames
+    }
+
+    #[test]
+    fn item_should_be_stored_in_single_file() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .storage_format(StorageFormat::V2)
+            .memory_tier(CacheLimits { max_total_bytes: None, max_entries: Some(10) })
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(60)).is_ok());
+
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+        assert_eq!(get_filenames(&namespace_path), vec![format!("{}-{}", name.as_ref(), ENTRY_FILENAME_POSTFIX)]);
+
+        assert_eq!(service.get::<Demo>(&namespace, &name).unwrap().unwrap(), demo);
+        assert_eq!(service.get::<Demo>(&namespace, &name).unwrap(), Some(demo));
+
+        let info = service.metadata(&namespace, &name).unwrap().unwrap();
+        assert!(info.payload_size > 0);
+        assert_eq!(info.ttl, Some(Duration::from_secs(60)));
+
+        assert!(service.touch(&namespace, &name, Some(Expiry::Never)).unwrap());
+        assert!(service.metadata(&namespace, &name).unwrap().unwrap().expires_at.is_none());
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        assert!(service.remove(&namespace, &name).unwrap());
+        assert!(get_filenames(&namespace_path).is_empty());
+    }
+
+    #[test]
+    fn items_of_both_formats_should_be_readable() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let v1_service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let v2_service = FileCacheService::builder(&root_path_str, &instance_name)
+            .storage_format(StorageFormat::V2)
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(v1_service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+        assert_eq!(v2_service.get::<Demo>(&namespace, &name).unwrap().unwrap(), demo);
+
+        // Item is rewritten in format v2, files of format v1 are removed
+        assert!(v2_service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+        assert_eq!(get_filenames(&namespace_path), vec![format!("{}-{}", name.as_ref(), ENTRY_FILENAME_POSTFIX)]);
+
+        assert_eq!(v1_service.get::<Demo>(&namespace, &name).unwrap().unwrap(), demo);
+        assert_eq!(v1_service.keys(&namespace).unwrap(), vec![name.as_ref().to_string()]);
+
+        assert!(v1_service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+        assert_eq!(get_filenames(&namespace_path), vec![
+            format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX),
+            format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX),
+        ]);
+
+        assert_eq!(v2_service.get::<Demo>(&namespace, &name).unwrap(), Some(demo));
+    }
+
+    #[test]
+    fn entry_with_checksum_mismatch_should_be_reported_as_corrupted() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .storage_format(StorageFormat::V2)
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let filename = format!("{}-{}", name.as_ref(), ENTRY_FILENAME_POSTFIX);
+        let entry_file_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(filename);
+
+        let mut data = fs::read(&entry_file_path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        fs::write(&entry_file_path, data).unwrap();
+
+        match service.get_detailed::<Demo>(&namespace, &name).unwrap() {
+            CacheLookup::Corrupted { path, reason } => {
+                assert_eq!(path, entry_file_path);
+                assert!(reason.contains("checksum"));
+            }
+            lookup => panic!("unexpected lookup result: {:?}", lookup)
+        }
+
+        assert!(!entry_file_path.exists());
+    }
+
+    #[test]
+    fn damaged_entry_header_should_be_corrupted_for_all_operations() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .storage_format(StorageFormat::V2)
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let filename = format!("{}-{}", name.as_ref(), ENTRY_FILENAME_POSTFIX);
+        let entry_file_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(filename);
+
+        // Expiration unix time in header
+        let mut data = fs::read(&entry_file_path).unwrap();
+        data[24] ^= 0x01;
+        fs::write(&entry_file_path, data).unwrap();
+
+        assert!(service.metadata(&namespace, &name).unwrap().is_none());
+        assert!(!service.touch(&namespace, &name, Some(Expiry::Never)).unwrap());
+
+        let report = service.purge_expired().unwrap();
+        assert_eq!(report.corrupted_entries, 1);
+        assert!(!entry_file_path.exists());
+    }
+
+    #[test]
+    fn expired_entry_should_be_purged_with_leftover_files() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .storage_format(StorageFormat::V2)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+
+        // Left by interrupted store in format v1
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+        fs::write(namespace_path.join(metadata_filename), "{}").unwrap();
+
+        let report = service.purge_expired().unwrap();
+        assert_eq!(report.expired_entries, 0);
+        assert_eq!(report.orphaned_files, 1);
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        clock.advance(Duration::from_secs(61));
+
+        let report = service.purge_expired().unwrap();
+        assert_eq!(report.expired_entries, 1);
+        assert!(report.bytes_freed > 0);
+        assert!(get_filenames(&namespace_path).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod get_tests {
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, init_env_logging};
+
+    #[test]
+    fn return_none_for_unknown_cache_item() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+}
+
+#[cfg(test)]
+mod store_tests {
+    use std::path::Path;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::expiry::Expiry;
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    #[test]
+    fn store_and_get() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        let result = service.get::<Demo>(&namespace, &name).unwrap().unwrap();
+
+        assert_eq!(result, demo);
+    }
+
+    #[test]
+    fn directory_hierarchy_should_be_created() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        assert!(
+            Path::new(root_path_str.as_ref())
+                .join(instance_name.as_ref())
+                .join(namespace.as_ref())
+                .exists()
+        );
+    }
+
+    #[test]
+    fn previous_cache_item_file_should_be_overwritten() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let first_item = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &first_item, Expiry::Never).is_ok());
+
+        let second_item = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &second_item, Expiry::Never).is_ok());
+
+        assert!(
+            Path::new(root_path_str.as_ref())
+                .join(instance_name.as_ref())
+                .join(namespace.as_ref())
+                .exists()
+        );
+    }
+}
+
+#[cfg(test)]
+mod new_tests {
+    use std::fs;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::service::FileCacheService;
+
+    #[test]
+    fn create_root_path_if_does_not_exist() {
+        let tmp_dir = tempdir().unwrap();
+        let root_path = tmp_dir.path();
+
+        fs::remove_dir(root_path).unwrap();
+
+        assert!(!root_path.exists());
+
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        assert!(root_path.exists());
+    }
+}
+
+#[cfg(test)]
+mod corrupted_data_tests {
+    use std::fs;
+    use std::path::Path;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::expiry::Expiry;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn corrupted_metadata_file_should_be_removed_with_cache_file_companion() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        let metadata_item_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref()).join(metadata_filename);
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+
+        let cache_item_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref()).join(filename);
+
+        fs::write(&metadata_item_path, "invalid-json-data").unwrap();
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+
+        assert!(!metadata_item_path.exists());
+        assert!(!cache_item_path.exists());
+    }
+
+    #[test]
+    fn corrupted_cache_file_should_be_removed_with_metadata_file_companion() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        let metadata_item_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref()).join(metadata_filename);
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+
+        let cache_item_path = Path::new(root_path_str.as_ref())
+                                        .join(instance_name.as_ref())
+                                        .join(namespace.as_ref()).join(filename);
+
+        fs::write(&cache_item_path, "invalid-json-data").unwrap();
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+
+        assert!(!metadata_item_path.exists());
+        assert!(!cache_item_path.exists());
+    }
+}
+
+#[cfg(test)]
+mod get_detailed_tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::error::FileCacheError;
+    use crate::expiry::Expiry;
+    use crate::lookup::CacheLookup;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn return_found_for_stored_item() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        match service.get_detailed::<Demo>(&namespace, &name).unwrap() {
+            CacheLookup::Found(value) => assert_eq!(value, demo),
+            lookup => panic!("unexpected lookup result: {:?}", lookup)
+        }
+    }
+
+    #[test]
+    fn return_missing_for_unknown_item() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(matches!(service.get_detailed::<Demo>(&namespace, &name).unwrap(), CacheLookup::Missing));
+    }
+
+    #[test]
+    fn return_expired_for_expired_item() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(1)).is_ok());
+
+        clock.advance(Duration::from_secs(3));
+
+        assert!(matches!(service.get_detailed::<Demo>(&namespace, &name).unwrap(), CacheLookup::Expired));
+        assert!(matches!(service.get_detailed::<Demo>(&namespace, &name).unwrap(), CacheLookup::Missing));
+    }
+
+    #[test]
+    fn return_corrupted_for_invalid_metadata() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        let metadata_item_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref()).join(metadata_filename);
+
+        fs::write(&metadata_item_path, "invalid-json-data").unwrap();
+
+        match service.get_detailed::<Demo>(&namespace, &name).unwrap() {
+            CacheLookup::Corrupted { path, .. } => assert_eq!(path, metadata_item_path),
+            lookup => panic!("unexpected lookup result: {:?}", lookup)
+        }
+    }
+
+    #[test]
+    fn return_corrupted_for_invalid_cache_file() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+
+        let cache_item_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref()).join(filename);
+
+        fs::write(&cache_item_path, "{\"login\": ").unwrap();
+
+        match service.get_detailed::<Demo>(&namespace, &name).unwrap() {
+            CacheLookup::Corrupted { path, .. } => assert_eq!(path, cache_item_path),
+            lookup => panic!("unexpected lookup result: {:?}", lookup)
+        }
+    }
+
+    #[test]
+    fn return_type_mismatch_for_item_of_another_type() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let lookup = service.get_detailed::<u64>(&namespace, &name).unwrap();
+
+        assert!(matches!(lookup, CacheLookup::TypeMismatch { .. }));
+        assert!(matches!(lookup.into_result(), Err(FileCacheError::SchemaMismatch { .. })));
+
+        // Item is removed as with `get`
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::error::FileCacheError;
+    use crate::expiry::Expiry;
+    use crate::key::encode_key;
+    use crate::lock::FileLock;
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn return_clock_error_for_time_before_unix_epoch() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::new(UNIX_EPOCH - Duration::from_secs(1));
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        match service.store(&namespace, &name, &get_demo_entity(), Expiry::Never) {
+            Err(FileCacheError::ClockError(_)) => {}
+            _ => panic!("clock error expected")
+        }
+    }
+
+    #[test]
+    fn return_lock_timeout_error_if_namespace_is_locked() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .lock_timeout(Duration::from_millis(100)).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let lock_file_path = service.get_lock_file_path(&encode_key(namespace.as_ref()).unwrap()).unwrap();
+        let _lock = FileLock::exclusive(&lock_file_path, None).unwrap();
+
+        match service.get::<Demo>(&namespace, &name) {
+            Err(FileCacheError::LockTimeout { path, .. }) => assert_eq!(path, lock_file_path),
+            _ => panic!("lock timeout error expected")
+        }
     }
 }
 
 #[cfg(test)]
-mod ttl_tests {
-    use std::fs;
+mod remove_tests {
     use std::path::Path;
-    use std::thread::sleep;
-    use std::time::Duration;
 
     use non_blank_string_rs::NonBlankString;
     use non_blank_string_rs::utils::get_random_nonblank_string;
     use tempfile::tempdir;
 
+    use crate::expiry::Expiry;
     use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService, METADATA_FILENAME_POSTFIX};
     use crate::tests::{Demo, get_demo_entity, init_env_logging};
 
     #[test]
-    fn delete_all_cache_item_file_if_metadata_is_missing() {
+    fn removed_item_should_not_be_returned() {
         init_env_logging();
 
         let root_path_tmp = tempdir().unwrap();
@@ -244,29 +2256,15 @@ mod ttl_tests {
 
         let demo = get_demo_entity();
 
-        assert!(service.store(&namespace, &name, &demo, 1000).is_ok());
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
 
-        let metadata_filename = format!("{}-{}", &name.as_ref(), METADATA_FILENAME_POSTFIX);
-        let metadata_file = Path::new(&root_path_str.as_ref())
-            .join(&instance_name.as_ref())
-            .join(&namespace.as_ref())
-            .join(metadata_filename);
-
-        fs::remove_file(metadata_file).unwrap();
+        assert!(service.remove(&namespace, &name).unwrap());
 
         assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
-
-        let cache_item_filename = format!("{}-{}", &name.as_ref(), CACHE_FILENAME_POSTFIX);
-        let cache_item_file = Path::new(&root_path_str.as_ref())
-            .join(&instance_name.as_ref())
-            .join(&namespace.as_ref())
-            .join(cache_item_filename);
-
-        assert!(!cache_item_file.exists());
     }
 
     #[test]
-    fn return_none_if_metadata_companion_file_is_missing() {
+    fn cache_item_and_metadata_files_should_be_removed() {
         init_env_logging();
 
         let root_path_tmp = tempdir().unwrap();
@@ -283,21 +2281,23 @@ mod ttl_tests {
 
         let demo = get_demo_entity();
 
-        assert!(service.store(&namespace, &name, &demo, 1000).is_ok());
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
 
-        let metadata_filename = format!("{}-{}", &name.as_ref(), METADATA_FILENAME_POSTFIX);
-        let metadata_file = Path::new(&root_path_str.as_ref())
-            .join(&instance_name.as_ref())
-            .join(&namespace.as_ref())
-            .join(metadata_filename);
+        assert!(service.remove(&namespace, &name).unwrap());
 
-        fs::remove_file(metadata_file).unwrap();
+        let namespace_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref());
 
-        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        assert!(!namespace_path.join(metadata_filename).exists());
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        assert!(!namespace_path.join(filename).exists());
     }
 
     #[test]
-    fn return_item_with_existing_ttl() {
+    fn return_false_for_unknown_cache_item() {
         init_env_logging();
 
         let root_path_tmp = tempdir().unwrap();
@@ -312,19 +2312,22 @@ mod ttl_tests {
         let namespace = get_random_nonblank_string();
         let name = get_random_nonblank_string();
 
-        let demo = get_demo_entity();
-
-        assert!(service.store(&namespace, &name, &demo, 1000).is_ok());
-
-        sleep(Duration::from_secs(1));
+        assert!(!service.remove(&namespace, &name).unwrap());
+    }
+}
 
-        let result = service.get::<Demo>(&namespace, &name).unwrap().unwrap();
+#[cfg(test)]
+mod invalidate_tests {
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
 
-        assert_eq!(result, demo);
-    }
+    use crate::expiry::Expiry;
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
 
     #[test]
-    fn return_none_for_item_with_expired_ttl() {
+    fn invalidate_namespace_should_remove_only_its_items() {
         init_env_logging();
 
         let root_path_tmp = tempdir().unwrap();
@@ -337,19 +2340,24 @@ mod ttl_tests {
             &root_path_str, &instance_name).unwrap();
 
         let namespace = get_random_nonblank_string();
+        let other_namespace = get_random_nonblank_string();
         let name = get_random_nonblank_string();
 
         let demo = get_demo_entity();
 
-        assert!(service.store(&namespace, &name, &demo, 1).is_ok());
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+        assert!(service.store(&other_namespace, &name, &demo, Expiry::Never).is_ok());
 
-        sleep(Duration::from_secs(3));
+        service.invalidate_namespace(&namespace).unwrap();
 
         assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+
+        let result = service.get::<Demo>(&other_namespace, &name).unwrap().unwrap();
+        assert_eq!(result, demo);
     }
 
     #[test]
-    fn remove_files_for_cache_item_with_expired_ttl() {
+    fn invalidate_instance_should_remove_all_namespaces() {
         init_env_logging();
 
         let root_path_tmp = tempdir().unwrap();
@@ -362,37 +2370,26 @@ mod ttl_tests {
             &root_path_str, &instance_name).unwrap();
 
         let namespace = get_random_nonblank_string();
+        let other_namespace = get_random_nonblank_string();
         let name = get_random_nonblank_string();
 
         let demo = get_demo_entity();
 
-        assert!(service.store(&namespace, &name, &demo, 1).is_ok());
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+        assert!(service.store(&other_namespace, &name, &demo, Expiry::Never).is_ok());
 
-        sleep(Duration::from_secs(3));
+        service.invalidate_instance().unwrap();
 
         assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+        assert!(service.get::<Demo>(&other_namespace, &name).unwrap().is_none());
 
-        let metadata_filename = format!("{}-{}", &name.as_ref(), METADATA_FILENAME_POSTFIX);
-        let metadata_file = Path::new(&root_path_str.as_ref())
-            .join(&instance_name.as_ref())
-            .join(&namespace.as_ref())
-            .join(metadata_filename);
-
-        assert!(!metadata_file.exists());
-
-        let cache_item_filename = format!("{}-{}", &name.as_ref(), CACHE_FILENAME_POSTFIX);
-        let cache_item_file = Path::new(&root_path_str.as_ref())
-            .join(&instance_name.as_ref())
-            .join(&namespace.as_ref())
-            .join(cache_item_filename);
-
-        assert!(!cache_item_file.exists());
+        let instance_path = root_path.join(instance_name.as_ref());
+        assert!(!instance_path.join(namespace.as_ref()).exists());
+        assert!(!instance_path.join(other_namespace.as_ref()).exists());
     }
 
     #[test]
-    fn item_should_be_retrieved_with_zero_ttl() {
-        init_env_logging();
-
+    fn invalidate_unknown_namespace_should_be_ok() {
         let root_path_tmp = tempdir().unwrap();
         let root_path = root_path_tmp.path();
         let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
@@ -403,31 +2400,33 @@ mod ttl_tests {
             &root_path_str, &instance_name).unwrap();
 
         let namespace = get_random_nonblank_string();
-        let name = get_random_nonblank_string();
-
-        let demo = get_demo_entity();
-
-        assert!(service.store(&namespace, &name, &demo, 0).is_ok());
 
-        sleep(Duration::from_secs(1));
-
-        let result = service.get::<Demo>(&namespace, &name).unwrap().unwrap();
-
-        assert_eq!(result, demo);
+        assert!(service.invalidate_namespace(&namespace).is_ok());
     }
 }
 
 #[cfg(test)]
-mod get_tests {
+mod concurrency_tests {
+    use std::sync::Arc;
+    use std::thread;
+
     use non_blank_string_rs::NonBlankString;
     use non_blank_string_rs::utils::get_random_nonblank_string;
     use tempfile::tempdir;
 
+    use crate::expiry::Expiry;
     use crate::service::FileCacheService;
-    use crate::tests::{Demo, init_env_logging};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    fn assert_send_sync<T: Send + Sync>() {}
 
     #[test]
-    fn return_none_for_unknown_cache_item() {
+    fn service_should_be_send_and_sync() {
+        assert_send_sync::<FileCacheService>();
+    }
+
+    #[test]
+    fn concurrent_store_and_get_should_return_complete_items() {
         init_env_logging();
 
         let root_path_tmp = tempdir().unwrap();
@@ -436,33 +2435,50 @@ mod get_tests {
 
         let instance_name = get_random_nonblank_string();
 
-        let service = FileCacheService::new(
-            &root_path_str, &instance_name).unwrap();
+        let service = Arc::new(FileCacheService::new(
+            &root_path_str, &instance_name).unwrap());
 
         let namespace = get_random_nonblank_string();
         let name = get_random_nonblank_string();
 
-        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+        let handles: Vec<_> = (0..8).map(|_| {
+            let service = service.clone();
+            let namespace = NonBlankString::parse(namespace.as_ref()).unwrap();
+            let name = NonBlankString::parse(name.as_ref()).unwrap();
+
+            thread::spawn(move || {
+                for _ in 0..20 {
+                    service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).unwrap();
+                    assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+                }
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
     }
 }
 
 #[cfg(test)]
-mod store_tests {
-    use std::path::Path;
+mod key_tests {
+    use std::fs;
 
     use non_blank_string_rs::NonBlankString;
     use non_blank_string_rs::utils::get_random_nonblank_string;
     use tempfile::tempdir;
 
+    use crate::error::FileCacheError;
+    use crate::expiry::Expiry;
     use crate::service::FileCacheService;
     use crate::tests::{Demo, get_demo_entity, init_env_logging};
 
     #[test]
-    fn store_and_get() {
+    fn path_traversal_names_should_stay_inside_namespace_directory() {
         init_env_logging();
 
         let root_path_tmp = tempdir().unwrap();
-        let root_path = root_path_tmp.path();
+        let root_path = root_path_tmp.path().join("cache");
         let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
 
         let instance_name = get_random_nonblank_string();
@@ -470,20 +2486,26 @@ mod store_tests {
         let service = FileCacheService::new(
             &root_path_str, &instance_name).unwrap();
 
-        let namespace = get_random_nonblank_string();
-        let name = get_random_nonblank_string();
+        let namespace = NonBlankString::parse("../..").unwrap();
+        let name = NonBlankString::parse("../../escape/x").unwrap();
 
         let demo = get_demo_entity();
 
-        assert!(service.store(&namespace, &name, &demo, 0).is_ok());
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
 
         let result = service.get::<Demo>(&namespace, &name).unwrap().unwrap();
-
         assert_eq!(result, demo);
+
+        let outside_entries: Vec<_> = fs::read_dir(root_path_tmp.path()).unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+
+        assert_eq!(outside_entries, vec!["cache".to_string()]);
     }
 
     #[test]
-    fn directory_hierarchy_should_be_created() {
+    fn slash_in_name_should_not_create_nested_directories() {
         let root_path_tmp = tempdir().unwrap();
         let root_path = root_path_tmp.path();
         let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
@@ -494,22 +2516,19 @@ mod store_tests {
             &root_path_str, &instance_name).unwrap();
 
         let namespace = get_random_nonblank_string();
-        let name = get_random_nonblank_string();
+        let name = NonBlankString::parse("users/123").unwrap();
 
-        let demo = get_demo_entity();
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
 
-        assert!(service.store(&namespace, &name, &demo, 0).is_ok());
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
 
-        assert!(
-            Path::new(&root_path_str.as_ref())
-                .join(instance_name.as_ref())
-                .join(namespace.as_ref())
-                .exists()
-        );
+        for entry in fs::read_dir(namespace_path).unwrap() {
+            assert!(entry.unwrap().file_type().unwrap().is_file());
+        }
     }
 
     #[test]
-    fn previous_cache_item_file_should_be_overwritten() {
+    fn return_invalid_key_error_for_too_long_name() {
         let root_path_tmp = tempdir().unwrap();
         let root_path = root_path_tmp.path();
         let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
@@ -519,69 +2538,221 @@ mod store_tests {
         let service = FileCacheService::new(
             &root_path_str, &instance_name).unwrap();
 
+        let namespace = get_random_nonblank_string();
+        let name = NonBlankString::parse(&"x".repeat(1000)).unwrap();
+
+        match service.store(&namespace, &name, &get_demo_entity(), Expiry::Never) {
+            Err(FileCacheError::InvalidKey(_)) => {}
+            _ => panic!("invalid key error expected")
+        }
+    }
+}
+
+#[cfg(test)]
+mod codec_tests {
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+
+    use crate::codec::{Codec, CodecKind};
+    use crate::error::FileCacheError;
+    use crate::expiry::Expiry;
+    use crate::lookup::CacheLookup;
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+    use crate::types::OperationResult;
+
+    #[test]
+    fn cache_file_extension_should_reflect_codec() {
+        init_env_logging();
+
+        for codec in CodecKind::all() {
+            let root_path_tmp = tempdir().unwrap();
+            let root_path = root_path_tmp.path();
+            let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+            let instance_name = get_random_nonblank_string();
+
+            let service = FileCacheService::builder(
+                &root_path_str, &instance_name).codec(*codec).build().unwrap();
+
+            let namespace = get_random_nonblank_string();
+            let name = get_random_nonblank_string();
+
+            let demo = get_demo_entity();
+
+            assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+            let filename = format!("{}-cache.{}", name.as_ref(), codec.extension());
+
+            assert!(
+                root_path.join(instance_name.as_ref())
+                    .join(namespace.as_ref())
+                    .join(filename)
+                    .exists()
+            );
+
+            let result = service.get::<Demo>(&namespace, &name).unwrap().unwrap();
+            assert_eq!(result, demo);
+        }
+    }
+
+    #[test]
+    fn items_should_be_decoded_with_codec_they_were_stored_with() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
         let namespace = get_random_nonblank_string();
         let name = get_random_nonblank_string();
 
-        let first_item = get_demo_entity();
+        let reader = FileCacheService::new(&root_path_str, &instance_name).unwrap();
 
-        assert!(service.store(&namespace, &name, &first_item, 0).is_ok());
+        for codec in CodecKind::all() {
+            let writer = FileCacheService::builder(
+                &root_path_str, &instance_name).codec(*codec).build().unwrap();
 
-        let second_item = get_demo_entity();
+            let demo = get_demo_entity();
 
-        assert!(service.store(&namespace, &name, &second_item, 0).is_ok());
+            assert!(writer.store(&namespace, &name, &demo, Expiry::Never).is_ok());
 
-        assert!(
-            Path::new(&root_path_str.as_ref())
-                .join(instance_name.as_ref())
-                .join(namespace.as_ref())
-                .exists()
-        );
+            let result = reader.get::<Demo>(&namespace, &name).unwrap().unwrap();
+            assert_eq!(result, demo);
+        }
+
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+
+        // Cache file of previous codec is replaced
+        assert_eq!(std::fs::read_dir(namespace_path).unwrap().count(), 2);
+    }
+
+    /// JSON with reversed bytes, stands for format which isn't built in
+    struct ReversedJsonCodec;
+
+    impl Codec for ReversedJsonCodec {
+        fn encode<T: Serialize + ?Sized>(&self, item: &T) -> OperationResult<Vec<u8>> {
+            let mut data = serde_json::to_vec(item)?;
+            data.reverse();
+            Ok(data)
+        }
+
+        fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> OperationResult<T> {
+            let mut data = data.to_vec();
+            data.reverse();
+            Ok(serde_json::from_slice(&data)?)
+        }
+    }
+
+    #[test]
+    fn item_of_custom_codec_should_be_read_with_same_codec() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store_with_codec(&namespace, &name, &demo, Expiry::Never, &ReversedJsonCodec).is_ok());
+
+        let metadata = service.metadata(&namespace, &name).unwrap().unwrap();
+        assert_eq!(metadata.codec, CodecKind::Custom);
+
+        let result = service.get_with_codec::<Demo>(&namespace, &name, &ReversedJsonCodec).unwrap().unwrap();
+        assert_eq!(result, demo);
+
+        // Plain get can't decode it
+        assert!(matches!(service.get_detailed::<Demo>(&namespace, &name).unwrap(), CacheLookup::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn custom_codec_should_not_be_set_for_service() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let result = FileCacheService::builder(&root_path_str, &instance_name).codec(CodecKind::Custom).build();
+
+        assert!(matches!(result, Err(FileCacheError::InvalidConfig(_))));
     }
 }
 
 #[cfg(test)]
-mod new_tests {
-    use std::fs;
-
+mod compression_tests {
     use non_blank_string_rs::NonBlankString;
     use non_blank_string_rs::utils::get_random_nonblank_string;
     use tempfile::tempdir;
 
+    use crate::compression::Compression;
+    use crate::expiry::Expiry;
     use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
 
     #[test]
-    fn create_root_path_if_does_not_exist() {
-        let tmp_dir = tempdir().unwrap();
-        let root_path = tmp_dir.path();
-
-        fs::remove_dir(root_path).unwrap();
-
-        assert!(!root_path.exists());
+    fn items_with_different_compression_should_live_side_by_side() {
+        init_env_logging();
 
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
         let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
 
         let instance_name = get_random_nonblank_string();
+        let namespace = get_random_nonblank_string();
 
-        FileCacheService::new(&root_path_str, &instance_name).unwrap();
+        let reader = FileCacheService::new(&root_path_str, &instance_name).unwrap();
 
-        assert!(root_path.exists());
+        let items: Vec<(NonBlankString, Demo)> = Compression::all().iter().map(|compression| {
+            let writer = FileCacheService::builder(
+                &root_path_str, &instance_name).compression(*compression).build().unwrap();
+
+            let name = get_random_nonblank_string();
+            let demo = get_demo_entity();
+
+            assert!(writer.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+            (name, demo)
+        }).collect();
+
+        for (name, demo) in items {
+            let result = reader.get::<Demo>(&namespace, &name).unwrap().unwrap();
+            assert_eq!(result, demo);
+        }
     }
 }
 
 #[cfg(test)]
-mod corrupted_data_tests {
+mod expiry_tests {
     use std::fs;
-    use std::path::Path;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
     use non_blank_string_rs::NonBlankString;
     use non_blank_string_rs::utils::get_random_nonblank_string;
     use tempfile::tempdir;
 
-    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService, METADATA_FILENAME_POSTFIX};
-    use crate::tests::{Demo, get_demo_entity};
+    use crate::expiry::Expiry;
+    use crate::service::{FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
 
     #[test]
-    fn corrupted_metadata_file_should_be_removed_with_cache_file_companion() {
+    fn item_with_past_expiration_time_should_not_be_returned() {
+        init_env_logging();
+
         let root_path_tmp = tempdir().unwrap();
         let root_path = root_path_tmp.path();
         let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
@@ -594,32 +2765,42 @@ mod corrupted_data_tests {
         let namespace = get_random_nonblank_string();
         let name = get_random_nonblank_string();
 
-        let demo = get_demo_entity();
+        let expires_at = UNIX_EPOCH + Duration::from_secs(1000);
 
-        assert!(service.store(&namespace, &name, &demo, 0).is_ok());
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::At(expires_at)).is_ok());
 
-        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
 
-        let metadata_item_path = Path::new(root_path_str.as_ref())
-            .join(instance_name.as_ref())
-            .join(namespace.as_ref()).join(metadata_filename);
+    #[test]
+    fn item_with_future_expiration_time_should_be_returned() {
+        init_env_logging();
 
-        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
 
-        let cache_item_path = Path::new(root_path_str.as_ref())
-            .join(instance_name.as_ref())
-            .join(namespace.as_ref()).join(filename);
+        let instance_name = get_random_nonblank_string();
 
-        fs::write(&metadata_item_path, "invalid-json-data").unwrap();
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
 
-        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
 
-        assert!(!metadata_item_path.exists());
-        assert!(!cache_item_path.exists());
+        let demo = get_demo_entity();
+
+        let expires_at = SystemTime::now() + Duration::from_secs(3600);
+
+        assert!(service.store(&namespace, &name, &demo, expires_at).is_ok());
+
+        assert_eq!(service.get::<Demo>(&namespace, &name).unwrap().unwrap(), demo);
     }
 
     #[test]
-    fn corrupted_cache_file_should_be_removed_with_metadata_file_companion() {
+    fn legacy_metadata_without_expires_at_should_be_supported() {
+        init_env_logging();
+
         let root_path_tmp = tempdir().unwrap();
         let root_path = root_path_tmp.path();
         let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
@@ -630,29 +2811,25 @@ mod corrupted_data_tests {
             &root_path_str, &instance_name).unwrap();
 
         let namespace = get_random_nonblank_string();
-        let name = get_random_nonblank_string();
+        let immortal_name = get_random_nonblank_string();
+        let expired_name = get_random_nonblank_string();
 
         let demo = get_demo_entity();
 
-        assert!(service.store(&namespace, &name, &demo, 0).is_ok());
+        assert!(service.store(&namespace, &immortal_name, &demo, Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &expired_name, &demo, Expiry::Never).is_ok());
 
-        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
-
-        let metadata_item_path = Path::new(root_path_str.as_ref())
-            .join(instance_name.as_ref())
-            .join(namespace.as_ref()).join(metadata_filename);
-
-        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
-
-        let cache_item_path = Path::new(root_path_str.as_ref())
-                                        .join(instance_name.as_ref())
-                                        .join(namespace.as_ref()).join(filename);
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
 
-        fs::write(&cache_item_path, "invalid-json-data").unwrap();
+        let immortal_metadata_filename = format!("{}-{}", immortal_name.as_ref(), METADATA_FILENAME_POSTFIX);
+        fs::write(namespace_path.join(immortal_metadata_filename),
+                  r#"{"ttl_secs":0,"created_unixtime":1000}"#).unwrap();
 
-        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+        let expired_metadata_filename = format!("{}-{}", expired_name.as_ref(), METADATA_FILENAME_POSTFIX);
+        fs::write(namespace_path.join(expired_metadata_filename),
+                  r#"{"ttl_secs":60,"created_unixtime":1000}"#).unwrap();
 
-        assert!(!metadata_item_path.exists());
-        assert!(!cache_item_path.exists());
+        assert_eq!(service.get::<Demo>(&namespace, &immortal_name).unwrap().unwrap(), demo);
+        assert!(service.get::<Demo>(&namespace, &expired_name).unwrap().is_none());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,835 @@
+use std::future::Future;
+use std::io;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use log::{debug, info, warn};
+use non_blank_string_rs::NonBlankString;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::fs;
+
+use crate::codec::CodecKind;
+use crate::error::{FileCacheError, GetOrInsertError};
+use crate::expiry::Expiry;
+use crate::file::{get_file_size_async, persist_temp_file_async, sync_dir_async, write_file_atomically_async, write_temp_file_async};
+use crate::format::{decode_entry, encode_entry, StorageFormat};
+use crate::key::encode_key;
+use crate::lock::{FileLock, LOCK_FILENAME_POSTFIX};
+use crate::memory::FileStamp;
+use crate::policy::NamespacePolicy;
+use crate::service::{CacheEntryState, decode_cache_data, FileCacheItemMetadata, FileCacheService, parse_metadata, RawCacheEntry};
+use crate::service::eviction::StoredSize;
+use crate::service::policy::parse_namespace_policy;
+use crate::service::touch::AccessUpdate;
+use crate::types::{EmptyResult, OperationResult, OptionalResult};
+
+/// Async file cache service, cargo feature `tokio`.
+///
+/// Mirrors `FileCacheService` with `tokio::fs` and non-blocking namespace locks.
+/// File hierarchy, metadata format and locks are the same, so sync and async services
+/// can share cache root.
+///
+/// Encoding and decoding of items run on blocking thread pool (`spawn_blocking`), as well as rare paths
+/// with sync service: cleanup of expired, orphaned and corrupted items, eviction when limits are set.
+#[derive(Clone)]
+pub struct AsyncFileCacheService {
+    service: FileCacheService,
+}
+
+impl AsyncFileCacheService {
+    /// Async service with settings of `service`
+    pub fn new(service: FileCacheService) -> AsyncFileCacheService {
+        AsyncFileCacheService { service }
+    }
+
+    /// Sync service with the same settings, for operations without async version
+    pub fn sync(&self) -> &FileCacheService {
+        &self.service
+    }
+
+    /// Same as `FileCacheService::store`. Item is taken by value: it's encoded on blocking thread pool
+    pub async fn store<T>(&self, namespace: &NonBlankString, name: &NonBlankString, item: T,
+                          expiry: impl Into<Expiry>) -> EmptyResult
+        where T: Serialize + Send + 'static {
+        self.store_item(namespace.as_ref(), name.as_ref(), item, Some(expiry.into()), None).await?;
+        Ok(())
+    }
+
+    /// Same as `FileCacheService::store_default`, item is taken by value as with `store`
+    pub async fn store_default<T>(&self, namespace: &NonBlankString, name: &NonBlankString,
+                                  item: T) -> EmptyResult
+        where T: Serialize + Send + 'static {
+        self.store_item(namespace.as_ref(), name.as_ref(), item, None, None).await?;
+        Ok(())
+    }
+
+    /// Same as `FileCacheService::get`
+    pub async fn get<T>(&self, namespace: &NonBlankString, item_name: &NonBlankString) -> OptionalResult<T>
+        where T: DeserializeOwned + Send + 'static {
+        Ok(self.get_entry(namespace.as_ref(), item_name.as_ref()).await?.map(|(value, _)| value))
+    }
+
+    /// Same as `FileCacheService::remove`
+    pub async fn remove(&self, namespace: &NonBlankString, name: &NonBlankString) -> OperationResult<bool> {
+        info!("remove entity from file cache: namespace='{}', item_name='{}'", namespace.as_ref(), name.as_ref());
+
+        let namespace_dirname = encode_key(namespace.as_ref())?;
+        let cache_item_path = self.service.get_cache_item_path(&namespace_dirname);
+
+        if !fs::try_exists(&cache_item_path).await? {
+            info!("file cache entity '{}' wasn't found", name.as_ref());
+            return Ok(false)
+        }
+
+        let _lock = self.lock_namespace_exclusive(&namespace_dirname).await?;
+
+        self.service.forget_in_memory(&cache_item_path, name.as_ref())?;
+
+        let mut removed = remove_file_if_exists(&self.service.get_entry_file_path(&cache_item_path, name.as_ref())?).await?;
+        removed |= remove_file_if_exists(&self.service.get_metadata_file_path(&cache_item_path, name.as_ref())?).await?;
+
+        for codec in CodecKind::all() {
+            let file_path = self.service.get_codec_file_path(&cache_item_path, name.as_ref(), *codec)?;
+            removed |= remove_file_if_exists(&file_path).await?;
+        }
+
+        if removed {
+            info!("item '{}' has been removed from file cache", name.as_ref());
+
+        } else {
+            info!("file cache entity '{}' wasn't found", name.as_ref());
+        }
+
+        Ok(removed)
+    }
+
+    /// Same as `FileCacheService::get_or_insert_with`, `init` is async
+    pub async fn get_or_insert_with<T, F, Fut>(&self, namespace: &NonBlankString, name: &NonBlankString,
+                                               expiry: impl Into<Expiry>, init: F) -> OperationResult<T>
+        where T: Serialize + DeserializeOwned + Send + 'static, F: FnOnce() -> Fut, Fut: Future<Output = T> {
+        let init = || async { Ok::<T, FileCacheError>(init().await) };
+
+        match self.get_or_try_insert_with(namespace, name, expiry, init).await {
+            Ok(value) => Ok(value),
+            Err(GetOrInsertError::Cache(e)) | Err(GetOrInsertError::Init(e)) => Err(e)
+        }
+    }
+
+    /// Same as `FileCacheService::get_or_try_insert_with`, `init` is async
+    pub async fn get_or_try_insert_with<T, E, F, Fut>(&self, namespace: &NonBlankString, name: &NonBlankString,
+                                                      expiry: impl Into<Expiry>, init: F) -> Result<T, GetOrInsertError<E>>
+        where T: Serialize + DeserializeOwned + Send + 'static, F: FnOnce() -> Fut, Fut: Future<Output = Result<T, E>> {
+        // Item due for early refresh and its creation time
+        let current = match self.get_entry::<T>(namespace.as_ref(), name.as_ref()).await? {
+            Some((value, metadata)) => {
+                if !self.service.is_early_refresh_due(&metadata)? {
+                    return Ok(value)
+                }
+
+                debug!("item '{}' is due for early refresh", name.as_ref());
+                Some((value, metadata.created_unixtime))
+            }
+            None => None
+        };
+
+        let namespace_dirname = encode_key(namespace.as_ref())?;
+        let item_dirname = encode_key(name.as_ref())?;
+
+        let _flight = self.service.async_single_flight.enter(format!("{}/{}", namespace_dirname, item_dirname)).await;
+        let _item_lock = self.lock_item_flight(&namespace_dirname, &item_dirname).await?;
+
+        // Value could have been stored while waiting for flight
+        if let Some((value, metadata)) = self.get_entry::<T>(namespace.as_ref(), name.as_ref()).await? {
+            if current.as_ref().is_none_or(|(_, created_unixtime)| metadata.created_unixtime != *created_unixtime) {
+                return Ok(value)
+            }
+        }
+
+        debug!("compute value for item '{}'", name.as_ref());
+
+        let started = Instant::now();
+
+        let value = match init().await {
+            Ok(value) => value,
+            Err(e) => {
+                if let Some((value, _)) = current {
+                    warn!("early refresh has failed for item '{}', current value is returned", name.as_ref());
+                    return Ok(value)
+                }
+
+                let namespace = namespace.as_ref().to_string();
+                let item_name = name.as_ref().to_string();
+
+                let stale_value = self.run_blocking(
+                    move |service| service.read_stale_item::<T>(&namespace, &item_name)).await?;
+
+                return match stale_value {
+                    Some(value) => {
+                        warn!("value init has failed for item '{}', stale value is returned", name.as_ref());
+                        Ok(value)
+                    }
+                    None => Err(GetOrInsertError::Init(e))
+                }
+            }
+        };
+
+        let compute_time_ms = started.elapsed().as_millis() as u64;
+
+        let value = self.store_item(namespace.as_ref(), name.as_ref(), value, Some(expiry.into()), Some(compute_time_ms)).await?;
+
+        Ok(value)
+    }
+
+    /// Item with its metadata, same as `FileCacheService::get_entry`
+    async fn get_entry<T>(&self, namespace: &str, item_name: &str) -> OptionalResult<(T, FileCacheItemMetadata)>
+        where T: DeserializeOwned + Send + 'static {
+        info!("get entity from file cache: namespace='{}', item_name='{}'", namespace, item_name);
+
+        let namespace_dirname = encode_key(namespace)?;
+        let cache_item_path = self.service.get_cache_item_path(&namespace_dirname);
+
+        if !fs::try_exists(&cache_item_path).await? {
+            info!("file cache entity '{}' wasn't found", item_name);
+            return Ok(None)
+        }
+
+        let (state, access_update) = {
+            let _lock = self.lock_namespace_shared(&namespace_dirname).await?;
+
+            let state = self.read_cache_entry::<T>(&cache_item_path, item_name).await?;
+
+            let access_update = match &state {
+                CacheEntryState::Found { metadata, .. } =>
+                    self.get_access_update(&namespace_dirname, &cache_item_path, item_name, metadata).await?,
+                _ => None
+            };
+
+            (state, access_update)
+        };
+
+        let state = match (state, access_update) {
+            (CacheEntryState::Found { value, metadata }, Some(update)) => {
+                let metadata = self.update_on_access(&namespace_dirname, &cache_item_path, item_name, metadata, update).await?;
+                CacheEntryState::Found { value, metadata }
+            }
+            (state, _) => state
+        };
+
+        match state {
+            CacheEntryState::Found { value, metadata } => {
+                info!("entity '{}' has been loaded from file cache", item_name);
+                Ok(Some((value, metadata)))
+            }
+            CacheEntryState::Missing => {
+                info!("file cache entity '{}' wasn't found", item_name);
+                Ok(None)
+            }
+            _ => {
+                // Cleanup under exclusive lock according to corruption policy, same as sync service
+                let namespace = namespace.to_string();
+                let item_name = item_name.to_string();
+                self.run_blocking(move |service| service.get_entry::<T>(&namespace, &item_name)).await
+            }
+        }
+    }
+
+    /// Item is serialized and compressed on blocking thread pool, returned back after store
+    async fn store_item<T>(&self, namespace: &str, name: &str, item: T,
+                           expiry: Option<Expiry>, compute_time_ms: Option<u64>) -> OperationResult<T>
+        where T: Serialize + Send + 'static {
+        let service = &self.service;
+
+        let namespace_dirname = encode_key(namespace)?;
+        let policy = self.read_namespace_policy(&namespace_dirname).await?.unwrap_or_default();
+        let expiry = expiry.or(policy.get_default_expiry()).unwrap_or(service.default_expiry);
+
+        info!("store entity '{}' into file cache", name);
+        let cache_item_path = service.get_cache_item_path(&namespace_dirname);
+
+        let (item, data, item_metadata) = self.run_blocking(move |service| {
+            let (data, item_metadata) = service.encode_item(&item, expiry, &policy, compute_time_ms)?;
+            Ok((item, data, item_metadata))
+        }).await?;
+
+        let stored = {
+            let _lock = self.lock_namespace_exclusive(&namespace_dirname).await?;
+
+            service.forget_in_memory(&cache_item_path, name)?;
+
+            fs::create_dir_all(&cache_item_path).await?;
+
+            let replaced_bytes = self.get_stored_size(&cache_item_path, name).await?;
+
+            let stored_bytes = match service.storage_format {
+                StorageFormat::V1 => self.write_v1_entry(&cache_item_path, name, &data, &item_metadata).await?,
+                StorageFormat::V2 => self.write_v2_entry(&cache_item_path, name, &data, &item_metadata).await?,
+            };
+
+            if service.write_options.fsync {
+                sync_dir_async(&cache_item_path).await?;
+            }
+
+            StoredSize { bytes: stored_bytes, replaced_bytes }
+        };
+
+        info!("item '{}' has been saved into file cache", name);
+
+        if service.has_limits() || !policy.limits.is_unlimited() {
+            let namespace = namespace.to_string();
+            let name = name.to_string();
+
+            self.run_blocking(move |service|
+                service.enforce_limits(&namespace, &namespace_dirname, &name, &stored, &policy)).await?;
+        }
+
+        Ok(item)
+    }
+
+    /// Async version of `FileCacheService::get_stored_size`. Caller must hold namespace lock.
+    async fn get_stored_size(&self, cache_item_path: &Path, name: &str) -> OptionalResult<u64> {
+        let service = &self.service;
+
+        let mut size_bytes = get_file_size_async(&service.get_entry_file_path(cache_item_path, name)?).await? +
+                             get_file_size_async(&service.get_metadata_file_path(cache_item_path, name)?).await?;
+
+        for codec in CodecKind::all() {
+            size_bytes += get_file_size_async(&service.get_codec_file_path(cache_item_path, name, *codec)?).await?;
+        }
+
+        Ok(Some(size_bytes).filter(|size_bytes| *size_bytes > 0))
+    }
+
+    /// Async version of `FileCacheService::write_v1_entry`. Caller must hold exclusive namespace lock.
+    async fn write_v1_entry(&self, cache_item_path: &Path, name: &str, data: &[u8],
+                            metadata: &FileCacheItemMetadata) -> OperationResult<u64> {
+        let service = &self.service;
+
+        let metadata_file_path = service.get_metadata_file_path(cache_item_path, name)?;
+        let file_path = service.get_codec_file_path(cache_item_path, name, metadata.codec)?;
+        let metadata_json = serde_json::to_string(metadata)?;
+
+        remove_file_if_exists(&service.get_entry_file_path(cache_item_path, name)?).await?;
+
+        // Same order as sync service: both files are written before renames, cache file goes first
+        let temp_file_path = write_temp_file_async(cache_item_path, data, &service.write_options).await?;
+
+        let metadata_temp_file_path = match write_temp_file_async(
+            cache_item_path, metadata_json.as_bytes(), &service.write_options).await {
+            Ok(metadata_temp_file_path) => metadata_temp_file_path,
+            Err(e) => {
+                remove_file_if_exists(&temp_file_path).await?;
+                return Err(e)
+            }
+        };
+
+        if let Err(e) = persist_temp_file_async(&temp_file_path, &file_path).await {
+            remove_file_if_exists(&metadata_temp_file_path).await?;
+            return Err(e)
+        }
+
+        persist_temp_file_async(&metadata_temp_file_path, &metadata_file_path).await?;
+
+        for previous_codec in CodecKind::all().iter().filter(|previous_codec| **previous_codec != metadata.codec) {
+            let previous_file_path = service.get_codec_file_path(cache_item_path, name, *previous_codec)?;
+            remove_file_if_exists(&previous_file_path).await?;
+        }
+
+        Ok((data.len() + metadata_json.len()) as u64)
+    }
+
+    /// Async version of `FileCacheService::write_v2_entry`. Caller must hold exclusive namespace lock.
+    async fn write_v2_entry(&self, cache_item_path: &Path, name: &str, data: &[u8],
+                            metadata: &FileCacheItemMetadata) -> OperationResult<u64> {
+        let service = &self.service;
+
+        let entry_file_path = service.get_entry_file_path(cache_item_path, name)?;
+
+        let entry = encode_entry(metadata, data);
+        let temp_file_path = write_temp_file_async(cache_item_path, &entry, &service.write_options).await?;
+        persist_temp_file_async(&temp_file_path, &entry_file_path).await?;
+
+        remove_file_if_exists(&service.get_metadata_file_path(cache_item_path, name)?).await?;
+
+        for codec in CodecKind::all() {
+            remove_file_if_exists(&service.get_codec_file_path(cache_item_path, name, *codec)?).await?;
+        }
+
+        Ok(entry.len() as u64)
+    }
+
+    /// Async version of `FileCacheService::read_cache_entry`: takes entry from memory tier
+    /// if file with metadata hasn't been replaced, payload is decoded on blocking thread pool.
+    /// Caller must hold namespace lock.
+    async fn read_cache_entry<T>(&self, cache_item_path: &Path, name: &str) -> OperationResult<CacheEntryState<T>>
+        where T: DeserializeOwned + Send + 'static {
+        let service = &self.service;
+
+        // Stamp is taken before files are read: concurrent write makes memory entry outdated, not vice versa
+        let stamp = match &service.memory_tier {
+            Some(_) => self.read_metadata_stamp(cache_item_path, name).await?,
+            None => None
+        };
+
+        let memory_entry = match (&service.memory_tier, &stamp) {
+            (Some(memory_tier), Some((stamp_path, stamp))) => memory_tier.get(stamp_path, stamp),
+            _ => None
+        };
+
+        let (metadata, file_path, data, from_memory) = match memory_entry {
+            Some((metadata, data)) => {
+                debug!("item '{}' has been found in memory tier", name);
+                let file_path = service.get_payload_file_path(cache_item_path, name, &metadata)?;
+                (metadata, file_path, data, true)
+            }
+            None => match self.read_entry_files(cache_item_path, name).await? {
+                Ok((metadata, file_path, data)) => (metadata, file_path, data, false),
+                Err(state) => return Ok(state)
+            }
+        };
+
+        let now_unixtime = service.get_now_in_unixtime_secs()?;
+
+        if metadata.is_expired(now_unixtime) {
+            return Ok(CacheEntryState::Expired { in_grace_period: !metadata.is_grace_period_over(now_unixtime) })
+        }
+
+        match (&service.memory_tier, stamp) {
+            (Some(memory_tier), Some((stamp_path, stamp))) if !from_memory => {
+                let state = decode_cache_data_blocking(file_path, data.clone(), metadata).await?;
+
+                if let CacheEntryState::Found { metadata, .. } = &state {
+                    memory_tier.insert(stamp_path, stamp, metadata.clone(), data);
+                }
+
+                Ok(state)
+            }
+            _ => decode_cache_data_blocking(file_path, data, metadata).await
+        }
+    }
+
+    /// Async version of `FileCacheService::read_entry_files`. Caller must hold namespace lock.
+    async fn read_entry_files<T>(&self, cache_item_path: &Path,
+                                 name: &str) -> OperationResult<Result<RawCacheEntry, CacheEntryState<T>>> {
+        let service = &self.service;
+
+        let entry_file_path = service.get_entry_file_path(cache_item_path, name)?;
+
+        if let Some(data) = read_file_if_exists(&entry_file_path).await? {
+            return match decode_entry(data) {
+                Ok((metadata, payload)) => Ok(Ok((metadata, entry_file_path, payload))),
+                Err(reason) => Ok(Err(CacheEntryState::Corrupted { path: entry_file_path, reason }))
+            }
+        }
+
+        let metadata_file_path = service.get_metadata_file_path(cache_item_path, name)?;
+
+        let metadata = match read_file_if_exists(&metadata_file_path).await? {
+            Some(metadata_json) => {
+                match parse_metadata(metadata_file_path, &metadata_json) {
+                    Ok(metadata) => metadata,
+                    Err(FileCacheError::Corrupted { path, reason }) => return Ok(Err(CacheEntryState::Corrupted { path, reason })),
+                    Err(e) => return Err(e)
+                }
+            }
+            None => {
+                for codec in CodecKind::all() {
+                    if fs::try_exists(service.get_codec_file_path(cache_item_path, name, *codec)?).await? {
+                        return Ok(Err(CacheEntryState::Orphaned))
+                    }
+                }
+
+                return Ok(Err(CacheEntryState::Missing))
+            }
+        };
+
+        let now_unixtime = service.get_now_in_unixtime_secs()?;
+
+        if metadata.is_expired(now_unixtime) {
+            return Ok(Err(CacheEntryState::Expired { in_grace_period: !metadata.is_grace_period_over(now_unixtime) }))
+        }
+
+        let file_path = service.get_codec_file_path(cache_item_path, name, metadata.codec)?;
+
+        match read_file_if_exists(&file_path).await? {
+            Some(data) => Ok(Ok((metadata, file_path, data))),
+            None => Ok(Err(CacheEntryState::Orphaned))
+        }
+    }
+
+    /// Async version of `FileCacheService::read_metadata_stamp`
+    async fn read_metadata_stamp(&self, cache_item_path: &Path, name: &str) -> OptionalResult<(PathBuf, FileStamp)> {
+        let entry_file_path = self.service.get_entry_file_path(cache_item_path, name)?;
+
+        if let Some(stamp) = FileStamp::read_async(&entry_file_path).await? {
+            return Ok(Some((entry_file_path, stamp)))
+        }
+
+        let metadata_file_path = self.service.get_metadata_file_path(cache_item_path, name)?;
+        Ok(FileStamp::read_async(&metadata_file_path).await?.map(|stamp| (metadata_file_path, stamp)))
+    }
+
+    /// Async version of `FileCacheService::get_access_update`. Caller must hold namespace lock.
+    async fn get_access_update(&self, namespace_dirname: &str, cache_item_path: &Path, name: &str,
+                               metadata: &FileCacheItemMetadata) -> OptionalResult<AccessUpdate> {
+        let service = &self.service;
+
+        let now_unixtime = service.get_now_in_unixtime_secs()?;
+
+        let access_tracked = metadata.get_last_access_unixtime() < now_unixtime &&
+            (service.has_limits() || self.read_namespace_policy(namespace_dirname).await?
+                .is_some_and(|policy| !policy.limits.is_unlimited()));
+
+        let Some(metadata) = service.get_accessed_metadata(metadata, now_unixtime, access_tracked) else {
+            return Ok(None)
+        };
+
+        Ok(self.read_metadata_stamp(cache_item_path, name).await?
+            .map(|(metadata_file_path, stamp)| AccessUpdate { metadata, metadata_file_path, stamp }))
+    }
+
+    /// Async version of `FileCacheService::update_on_access`, must be called without namespace lock
+    async fn update_on_access(&self, namespace_dirname: &str, cache_item_path: &Path, name: &str,
+                              metadata: FileCacheItemMetadata,
+                              update: AccessUpdate) -> OperationResult<FileCacheItemMetadata> {
+        let service = &self.service;
+
+        let lock_file_path = self.get_lock_file_path(namespace_dirname).await?;
+
+        let Some(_lock) = FileLock::try_exclusive_async(&lock_file_path).await? else {
+            debug!("namespace is locked, metadata update on access is skipped for item '{}'", name);
+            return Ok(metadata)
+        };
+
+        if FileStamp::read_async(&update.metadata_file_path).await? != Some(update.stamp) {
+            debug!("item '{}' has been rewritten, metadata update on access is skipped", name);
+            return Ok(metadata)
+        }
+
+        // Format v2: header is rewritten along with payload
+        let content = if update.metadata_file_path == service.get_entry_file_path(cache_item_path, name)? {
+            let data = fs::read(&update.metadata_file_path).await?;
+            let (_, payload) = decode_entry(data)
+                .map_err(|reason| FileCacheError::Corrupted { path: update.metadata_file_path.clone(), reason })?;
+            encode_entry(&update.metadata, &payload)
+
+        } else {
+            serde_json::to_vec(&update.metadata)?
+        };
+
+        write_file_atomically_async(&update.metadata_file_path, &content, &service.write_options).await?;
+
+        if let Some(memory_tier) = &service.memory_tier {
+            match FileStamp::read_async(&update.metadata_file_path).await? {
+                Some(stamp) => memory_tier.update_metadata(&update.metadata_file_path, stamp, update.metadata.clone()),
+                None => memory_tier.remove(&update.metadata_file_path)
+            }
+        }
+
+        debug!("metadata has been updated on access for item '{}'", name);
+        Ok(update.metadata)
+    }
+
+    async fn read_namespace_policy(&self, namespace_dirname: &str) -> OptionalResult<NamespacePolicy> {
+        let policy_file_path = self.service.get_policy_file_path(namespace_dirname);
+        let stamp = FileStamp::read_async(&policy_file_path).await?;
+
+        if let Some(policy) = self.service.policy_cache.get(namespace_dirname, &stamp) {
+            return Ok(policy)
+        }
+
+        let policy = match read_file_if_exists(&policy_file_path).await? {
+            Some(policy_json) => parse_namespace_policy(&policy_file_path, &policy_json),
+            None => None
+        };
+
+        self.service.policy_cache.insert(namespace_dirname, stamp, policy);
+        Ok(policy)
+    }
+
+    async fn lock_namespace_shared(&self, namespace_dirname: &str) -> OperationResult<FileLock> {
+        let lock_file_path = self.get_lock_file_path(namespace_dirname).await?;
+        FileLock::shared_async(&lock_file_path, self.service.lock_timeout).await
+    }
+
+    async fn lock_namespace_exclusive(&self, namespace_dirname: &str) -> OperationResult<FileLock> {
+        let lock_file_path = self.get_lock_file_path(namespace_dirname).await?;
+        FileLock::exclusive_async(&lock_file_path, self.service.lock_timeout).await
+    }
+
+    /// Async version of `FileCacheService::lock_item_flight`
+    async fn lock_item_flight(&self, namespace_dirname: &str, item_dirname: &str) -> OptionalResult<FileLock> {
+        if !self.service.cross_process_single_flight {
+            return Ok(None)
+        }
+
+        let namespace_locks_path = self.service.get_item_locks_path(namespace_dirname);
+        let lock_file_path = namespace_locks_path.join(format!("{}.{}", item_dirname, LOCK_FILENAME_POSTFIX));
+
+        loop {
+            fs::create_dir_all(&namespace_locks_path).await?;
+
+            match FileLock::exclusive_async(&lock_file_path, self.service.lock_timeout).await {
+                Ok(lock) => return Ok(Some(lock)),
+                // Item locks directory has been removed meanwhile
+                Err(FileCacheError::IOError(e)) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e)
+            }
+        }
+    }
+
+    async fn get_lock_file_path(&self, namespace_dirname: &str) -> OperationResult<PathBuf> {
+        fs::create_dir_all(self.service.get_locks_path()).await?;
+        Ok(self.service.get_namespace_lock_file_path(namespace_dirname))
+    }
+
+    /// Run sync service operation on blocking thread pool
+    async fn run_blocking<R, F>(&self, operation: F) -> OperationResult<R>
+        where R: Send + 'static, F: FnOnce(&FileCacheService) -> OperationResult<R> + Send + 'static {
+        let service = self.service.clone();
+
+        tokio::task::spawn_blocking(move || operation(&service)).await
+            .map_err(io::Error::from)?
+    }
+}
+
+/// `decode_cache_data` on blocking thread pool: decompression and deserialization of large items take a while
+async fn decode_cache_data_blocking<T>(file_path: PathBuf, data: Vec<u8>,
+                                       metadata: FileCacheItemMetadata) -> OperationResult<CacheEntryState<T>>
+    where T: DeserializeOwned + Send + 'static {
+    let state = tokio::task::spawn_blocking(move || decode_cache_data(file_path, data, metadata)).await
+        .map_err(io::Error::from)?;
+
+    Ok(state)
+}
+
+async fn read_file_if_exists(file_path: &Path) -> OptionalResult<Vec<u8>> {
+    match fs::read(file_path).await {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(FileCacheError::from(e))
+    }
+}
+
+async fn remove_file_if_exists(file_path: &Path) -> OperationResult<bool> {
+    match fs::remove_file(file_path).await {
+        Ok(_) => {
+            debug!("file '{}' has been removed", file_path.display());
+            Ok(true)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(FileCacheError::from(e))
+    }
+}
+
+#[cfg(test)]
+mod async_file_cache_service_tests {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::error::GetOrInsertError;
+    use crate::expiry::Expiry;
+    use crate::limits::CacheLimits;
+    use crate::service::{AsyncFileCacheService, CACHE_FILENAME_POSTFIX, FileCacheService};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    #[tokio::test]
+    async fn sync_and_async_services_should_share_cache() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+        let async_service = AsyncFileCacheService::new(service.clone());
+
+        let namespace = get_random_nonblank_string();
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+
+        let first = get_demo_entity();
+        let second = get_demo_entity();
+
+        assert!(async_service.store(&namespace, &first_name, first.clone(), Expiry::Never).await.is_ok());
+        assert!(service.store(&namespace, &second_name, &second, Expiry::Never).is_ok());
+
+        assert_eq!(service.get::<Demo>(&namespace, &first_name).unwrap(), Some(first));
+        assert_eq!(async_service.get::<Demo>(&namespace, &second_name).await.unwrap(), Some(second));
+
+        assert!(async_service.remove(&namespace, &first_name).await.unwrap());
+        assert!(!async_service.remove(&namespace, &first_name).await.unwrap());
+        assert!(service.get::<Demo>(&namespace, &first_name).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn item_should_be_read_from_memory() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .memory_tier(CacheLimits { max_total_bytes: None, max_entries: Some(100) })
+            .build().unwrap();
+        let async_service = AsyncFileCacheService::new(service.clone());
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+        assert_eq!(async_service.get::<Demo>(&namespace, &name).await.unwrap(), Some(demo.clone()));
+
+        // Cache file isn't read while metadata file is the same
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let file_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(filename);
+        std::fs::write(&file_path, "{").unwrap();
+
+        assert_eq!(async_service.get::<Demo>(&namespace, &name).await.unwrap(), Some(demo.clone()));
+        assert_eq!(service.get::<Demo>(&namespace, &name).unwrap(), Some(demo));
+
+        assert!(async_service.remove(&namespace, &name).await.unwrap());
+        assert!(async_service.get::<Demo>(&namespace, &name).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn expired_item_should_be_removed() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+        let async_service = AsyncFileCacheService::new(service.clone());
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(async_service.store(&namespace, &name, get_demo_entity(), Duration::from_secs(60)).await.is_ok());
+        assert!(async_service.get::<Demo>(&namespace, &name).await.unwrap().is_some());
+
+        clock.advance(Duration::from_secs(61));
+
+        assert!(async_service.get::<Demo>(&namespace, &name).await.unwrap().is_none());
+        assert!(!service.remove(&namespace, &name).unwrap());
+    }
+
+    #[tokio::test]
+    async fn value_should_be_computed_once() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = AsyncFileCacheService::new(FileCacheService::new(&root_path_str, &instance_name).unwrap());
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+        let calls = Cell::new(0);
+
+        let first = service.get_or_insert_with(&namespace, &name, Expiry::Never, || async {
+            calls.set(calls.get() + 1);
+            Demo { login: demo.login.clone() }
+        }).await.unwrap();
+
+        let second = service.get_or_insert_with(&namespace, &name, Expiry::Never, || async {
+            calls.set(calls.get() + 1);
+            get_demo_entity()
+        }).await.unwrap();
+
+        assert_eq!(calls.get(), 1);
+        assert_eq!(first, demo);
+        assert_eq!(second, demo);
+
+        let result = service.get_or_try_insert_with::<Demo, _, _, _>(
+            &namespace, &get_random_nonblank_string(), Expiry::Never, || async { Err("upstream is down") }).await;
+
+        assert!(matches!(result, Err(GetOrInsertError::Init(_))));
+    }
+
+    #[tokio::test]
+    async fn value_should_be_computed_once_for_concurrent_tasks() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let get_service = || AsyncFileCacheService::new(
+            FileCacheService::builder(&root_path_str, &instance_name)
+                .cross_process_single_flight(true)
+                .build().unwrap()
+        );
+
+        let service = get_service();
+
+        // Doesn't share in-process flights, as service of another process
+        let other_service = get_service();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let calls = Cell::new(0);
+
+        let init = || async {
+            calls.set(calls.get() + 1);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            get_demo_entity()
+        };
+
+        let (first, second, third) = tokio::join!(
+            service.get_or_insert_with(&namespace, &name, Expiry::Never, init),
+            service.get_or_insert_with(&namespace, &name, Expiry::Never, init),
+            other_service.get_or_insert_with(&namespace, &name, Expiry::Never, init)
+        );
+
+        let first = first.unwrap();
+
+        assert_eq!(calls.get(), 1);
+        assert_eq!(second.unwrap(), first);
+        assert_eq!(third.unwrap(), first);
+    }
+
+    #[tokio::test]
+    async fn limits_should_be_enforced() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .limits(CacheLimits { max_total_bytes: None, max_entries: Some(1) }).build().unwrap();
+        let async_service = AsyncFileCacheService::new(service);
+
+        let namespace = get_random_nonblank_string();
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+
+        assert!(async_service.store(&namespace, &first_name, get_demo_entity(), Expiry::Never).await.is_ok());
+        assert!(async_service.store(&namespace, &second_name, get_demo_entity(), Expiry::Never).await.is_ok());
+
+        assert!(async_service.get::<Demo>(&namespace, &first_name).await.unwrap().is_none());
+        assert!(async_service.get::<Demo>(&namespace, &second_name).await.unwrap().is_some());
+    }
+}
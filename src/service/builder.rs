@@ -0,0 +1,330 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use non_blank_string_rs::NonBlankString;
+
+use crate::clock::{Clock, SystemClock};
+use crate::codec::CodecKind;
+use crate::compression::Compression;
+use crate::config::{invalid_config, FileCacheConfig, FsyncPolicy};
+use crate::corruption::CorruptionPolicy;
+use crate::expiry::Expiry;
+use crate::file::WriteOptions;
+use crate::format::StorageFormat;
+use crate::limits::CacheLimits;
+use crate::memory::MemoryTier;
+use crate::service::FileCacheService;
+use crate::types::OperationResult;
+
+/// Builder of `FileCacheService` with validated settings.
+///
+/// Settings can be set one by one or taken from `FileCacheConfig`.
+#[derive(Clone)]
+pub struct FileCacheServiceBuilder {
+    config: FileCacheConfig,
+
+    clock: Arc<dyn Clock>,
+
+    /// Setting which can't be represented in config, reported by `build`
+    invalid_setting: Option<String>,
+}
+
+impl FileCacheServiceBuilder {
+    pub fn new(root_path: &NonBlankString, instance_name: &NonBlankString) -> FileCacheServiceBuilder {
+        FileCacheServiceBuilder::from_config(
+            FileCacheConfig::new(PathBuf::from(root_path.as_ref()), instance_name.as_ref())
+        )
+    }
+
+    /// Builder with settings from application config
+    pub fn from_config(config: FileCacheConfig) -> FileCacheServiceBuilder {
+        FileCacheServiceBuilder {
+            config,
+            clock: Arc::new(SystemClock),
+            invalid_setting: None,
+        }
+    }
+
+    /// Expiry of items stored with `store_default`. Default: never expire
+    pub fn default_ttl(mut self, ttl: Duration) -> FileCacheServiceBuilder {
+        // Rounded up to seconds as `Expiry::After`
+        self.config.default_ttl_secs = Some(get_secs_rounded_up(ttl));
+        self
+    }
+
+    /// Codec for new items. Default: `CodecKind::Json`
+    pub fn codec(mut self, codec: CodecKind) -> FileCacheServiceBuilder {
+        self.config.codec = codec;
+        self
+    }
+
+    /// Compression for new items. Default: `Compression::None`
+    pub fn compression(mut self, compression: Compression) -> FileCacheServiceBuilder {
+        self.config.compression = compression;
+        self
+    }
+
+    /// Unix permissions of cache files, e.g. `0o600` for owner only access.
+    /// Default: permissions of new files, same as `fs::write` (umask applies)
+    pub fn file_mode(mut self, file_mode: u32) -> FileCacheServiceBuilder {
+        self.config.file_mode = Some(file_mode);
+        self
+    }
+
+    /// Default: `FsyncPolicy::Always`
+    pub fn fsync(mut self, fsync: FsyncPolicy) -> FileCacheServiceBuilder {
+        self.config.fsync = fsync;
+        self
+    }
+
+    /// Limit whole cache instance (all namespaces). Default: unlimited
+    pub fn limits(mut self, limits: CacheLimits) -> FileCacheServiceBuilder {
+        self.config.limits = limits;
+        self
+    }
+
+    /// Limit single namespace, applied along with instance limits
+    pub fn namespace_limits(mut self, namespace: &NonBlankString,
+                            limits: CacheLimits) -> FileCacheServiceBuilder {
+        self.config.namespace_limits.insert(namespace.as_ref().to_string(), limits);
+        self
+    }
+
+    /// Default: `CorruptionPolicy::Delete`
+    pub fn corruption_policy(mut self, policy: CorruptionPolicy) -> FileCacheServiceBuilder {
+        self.config.corruption_policy = policy;
+        self
+    }
+
+    /// Remove quarantined items (see `CorruptionPolicy::Quarantine`) older than `retention`
+    /// with `FileCacheService::purge_expired`. Rounded up to seconds. Default: kept until removed manually
+    pub fn quarantine_retention(mut self, retention: Duration) -> FileCacheServiceBuilder {
+        self.config.quarantine_retention_secs = Some(get_secs_rounded_up(retention));
+        self
+    }
+
+    /// Max time to wait for namespace lock. Default: wait forever
+    pub fn lock_timeout(mut self, timeout: Duration) -> FileCacheServiceBuilder {
+        match u64::try_from(timeout.as_millis()) {
+            Ok(timeout_ms) => self.config.lock_timeout_ms = Some(timeout_ms),
+            Err(_) => self.invalid_setting = Some(format!("lock_timeout {:?} is too long", timeout))
+        }
+
+        self
+    }
+
+    /// Push expiration of item forward on each successful `get` by TTL set on store,
+    /// e.g. session expires after 30 minutes of inactivity. Only metadata file is rewritten.
+    /// Applies to items stored with `Expiry::After`, items stored with `Expiry::At` keep their expiration.
+    /// Default: disabled, TTL is counted from store
+    pub fn sliding_expiration(mut self, enabled: bool) -> FileCacheServiceBuilder {
+        self.config.sliding_expiration = enabled;
+        self
+    }
+
+    /// Keep expired items for `grace_period`, they can be read as stale with
+    /// `FileCacheService::get_allow_stale`. Rounded up to seconds. Default: no grace period
+    pub fn grace_period(mut self, grace_period: Duration) -> FileCacheServiceBuilder {
+        self.config.grace_period_secs = Some(get_secs_rounded_up(grace_period));
+        self
+    }
+
+    /// Coordinate `get_or_insert_with` across processes sharing cache root: only one process
+    /// computes an item, others wait on item lock file and read stored value.
+    /// Default: disabled, single-flight works within process only
+    pub fn cross_process_single_flight(mut self, enabled: bool) -> FileCacheServiceBuilder {
+        self.config.cross_process_single_flight = enabled;
+        self
+    }
+
+    /// Refresh items in `get_or_insert_with` before expiration with probability rising as expiration
+    /// approaches (XFetch), so refreshes of hot items are spread out instead of firing at expiration second.
+    /// Items which take longer to compute are refreshed earlier.
+    ///
+    /// - `beta` - `1.0` is a good default, `> 1.0` favors earlier refreshes
+    ///
+    /// Default: disabled, items are computed after expiration
+    pub fn early_refresh(mut self, beta: f64) -> FileCacheServiceBuilder {
+        self.config.early_refresh_beta = Some(beta);
+        self
+    }
+
+    /// Keep recently read items in memory in front of files, up to `limits` (least recently used are dropped).
+    /// `get` doesn't read files then, item is checked to be up to date by metadata file modification time
+    /// and inode, so writes of other processes are detected. Expiration rules are the same.
+    ///
+    /// Default: disabled
+    pub fn memory_tier(mut self, limits: CacheLimits) -> FileCacheServiceBuilder {
+        self.config.memory_limits = Some(limits);
+        self
+    }
+
+    /// Layout of new items on disk, see `StorageFormat`. Items of both formats are readable,
+    /// so format can be switched for existing cache: items are rewritten in new format on next store.
+    /// Default: `StorageFormat::V1`
+    pub fn storage_format(mut self, format: StorageFormat) -> FileCacheServiceBuilder {
+        self.config.storage_format = format;
+        self
+    }
+
+    /// Time source for expiration and metadata timestamps. Default: `SystemClock`
+    pub fn clock(mut self, clock: impl Clock + 'static) -> FileCacheServiceBuilder {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Validate settings and create service.
+    ///
+    /// Returns `FileCacheError::InvalidConfig` for invalid settings.
+    pub fn build(self) -> OperationResult<FileCacheService> {
+        if let Some(message) = self.invalid_setting {
+            return Err(invalid_config(&message))
+        }
+
+        let config = self.config;
+        config.validate()?;
+
+        let mut service = FileCacheService::create(&config.root_path, &config.instance_name)?;
+
+        service.default_expiry = match config.default_ttl_secs {
+            Some(ttl_secs) => Expiry::After(Duration::from_secs(ttl_secs)),
+            None => Expiry::Never
+        };
+        service.codec = config.codec;
+        service.compression = config.compression;
+        service.write_options = WriteOptions {
+            fsync: config.fsync == FsyncPolicy::Always,
+            file_mode: config.file_mode,
+        };
+        service.limits = config.limits;
+        service.namespace_limits = config.namespace_limits;
+        service.corruption_policy = config.corruption_policy;
+        service.quarantine_retention_secs = config.quarantine_retention_secs;
+        service.lock_timeout = config.lock_timeout_ms.map(Duration::from_millis);
+        service.sliding_expiration = config.sliding_expiration;
+        service.grace_period_secs = config.grace_period_secs;
+        service.cross_process_single_flight = config.cross_process_single_flight;
+        service.early_refresh_beta = config.early_refresh_beta;
+        service.memory_tier = config.memory_limits.map(|limits| Arc::new(MemoryTier::new(limits)));
+        service.storage_format = config.storage_format;
+        service.clock = self.clock;
+
+        Ok(service)
+    }
+}
+
+fn get_secs_rounded_up(duration: Duration) -> u64 {
+    let secs = duration.as_secs();
+
+    if duration.subsec_nanos() > 0 {
+        secs.saturating_add(1)
+    } else {
+        secs
+    }
+}
+
+#[cfg(test)]
+mod file_cache_service_builder_tests {
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::config::{FileCacheConfig, FsyncPolicy};
+    use crate::error::FileCacheError;
+    use crate::limits::CacheLimits;
+    use crate::service::FileCacheService;
+    use crate::service::builder::{get_secs_rounded_up, FileCacheServiceBuilder};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn store_default_should_use_default_ttl() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .default_ttl(Duration::from_secs(60))
+            .fsync(FsyncPolicy::Never)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store_default(&namespace, &name, &get_demo_entity()).is_ok());
+
+        clock.advance(Duration::from_secs(30));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        clock.advance(Duration::from_secs(60));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn service_should_be_built_from_config() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+
+        let instance_name = get_random_nonblank_string();
+
+        let mut config = FileCacheConfig::new(root_path, instance_name.as_ref());
+        config.limits = CacheLimits { max_total_bytes: None, max_entries: Some(1) };
+
+        let service = FileCacheServiceBuilder::from_config(config).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+
+        assert!(service.store_default(&namespace, &first_name, &get_demo_entity()).is_ok());
+        assert!(service.store_default(&namespace, &second_name, &get_demo_entity()).is_ok());
+
+        assert!(service.get::<Demo>(&namespace, &first_name).unwrap().is_none());
+        assert!(service.get::<Demo>(&namespace, &second_name).unwrap().is_some());
+    }
+
+    #[test]
+    fn invalid_config_should_be_rejected() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let result = FileCacheService::builder(&root_path_str, &instance_name)
+            .limits(CacheLimits { max_total_bytes: Some(0), max_entries: None })
+            .build();
+
+        assert!(matches!(result, Err(FileCacheError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn too_long_lock_timeout_should_be_rejected() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let result = FileCacheService::builder(&root_path_str, &instance_name)
+            .lock_timeout(Duration::MAX)
+            .build();
+
+        assert!(matches!(result, Err(FileCacheError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn durations_should_be_rounded_up_to_seconds() {
+        assert_eq!(get_secs_rounded_up(Duration::from_secs(5)), 5);
+        assert_eq!(get_secs_rounded_up(Duration::from_millis(5001)), 6);
+        assert_eq!(get_secs_rounded_up(Duration::MAX), u64::MAX);
+    }
+}
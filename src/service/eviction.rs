@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use log::{debug, info, warn};
+
+use crate::codec::CodecKind;
+use crate::error::FileCacheError;
+use crate::file::get_file_size;
+use crate::limits::CacheLimits;
+use crate::policy::NamespacePolicy;
+use crate::service::FileCacheService;
+use crate::types::{EmptyResult, OperationResult, OptionalResult};
+
+/// Usage is scanned again at least this often, so items stored by other processes are accounted
+const USAGE_RESCAN_INTERVAL_SECS: u64 = 60;
+
+/// Disk usage estimates of limited scopes between scans, shared by clones.
+/// Key - namespace directory name, `None` - instance.
+///
+/// `store` doesn't scan cache while estimate is within limits.
+#[derive(Clone, Default)]
+pub(super) struct UsageEstimates {
+    estimates: Arc<Mutex<HashMap<Option<String>, UsageEstimate>>>,
+}
+
+/// Usage as of last scan plus items stored since. Removals aren't subtracted,
+/// so estimate errs on the side of scanning. Overwritten items are accounted by size change.
+struct UsageEstimate {
+    total_bytes: u64,
+    entries: usize,
+    scanned_unixtime: u64,
+}
+
+impl UsageEstimates {
+    /// Account stored item. Returns `true` if usage has to be scanned:
+    /// estimate exceeds `limits`, is outdated or missing.
+    fn add_stored(&self, scope: Option<&str>, stored: &StoredSize, limits: &CacheLimits, now_unixtime: u64) -> bool {
+        let mut estimates = self.lock_estimates();
+
+        match estimates.get_mut(&scope.map(str::to_string)) {
+            Some(estimate) if now_unixtime.saturating_sub(estimate.scanned_unixtime) < USAGE_RESCAN_INTERVAL_SECS => {
+                estimate.total_bytes = estimate.total_bytes.saturating_add(stored.bytes);
+
+                match stored.replaced_bytes {
+                    Some(replaced_bytes) => estimate.total_bytes = estimate.total_bytes.saturating_sub(replaced_bytes),
+                    None => estimate.entries += 1
+                }
+
+                limits.is_exceeded(estimate.total_bytes, estimate.entries)
+            }
+            _ => true
+        }
+    }
+
+    fn set_scanned(&self, scope: Option<&str>, total_bytes: u64, entries: usize, now_unixtime: u64) {
+        self.lock_estimates().insert(scope.map(str::to_string),
+                                     UsageEstimate { total_bytes, entries, scanned_unixtime: now_unixtime });
+    }
+
+    fn lock_estimates(&self) -> MutexGuard<'_, HashMap<Option<String>, UsageEstimate>> {
+        self.estimates.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Size of just stored item on disk
+pub(super) struct StoredSize {
+    pub(super) bytes: u64,
+
+    /// Size of previous version of item, `None` - item is new
+    pub(super) replaced_bytes: Option<u64>,
+}
+
+/// Disk usage of cache item
+struct CacheEntryUsage {
+    namespace_dirname: String,
+
+    name: String,
+
+    /// Cache and metadata files size
+    size_bytes: u64,
+
+    created_unixtime: u64,
+
+    last_access_unixtime: u64,
+}
+
+impl FileCacheService {
+    /// Last access time is tracked only if any limits are set
+    pub(super) fn is_access_tracked(&self, namespace_dirname: &str) -> OperationResult<bool> {
+        if self.has_limits() {
+            return Ok(true)
+        }
+
+        let policy = self.read_namespace_policy(namespace_dirname)?;
+        Ok(policy.is_some_and(|policy| !policy.limits.is_unlimited()))
+    }
+
+    /// Instance or namespace limits are set (not counting namespace policies)
+    pub(super) fn has_limits(&self) -> bool {
+        !self.limits.is_unlimited() || self.namespace_limits.values().any(|limits| !limits.is_unlimited())
+    }
+
+    /// Size of item files on disk, `None` if item isn't stored. Caller must hold namespace lock.
+    pub(super) fn get_stored_size(&self, cache_item_path: &Path, name: &str) -> OptionalResult<u64> {
+        let mut size_bytes = get_file_size(&self.get_entry_file_path(cache_item_path, name)?)? +
+                             get_file_size(&self.get_metadata_file_path(cache_item_path, name)?)?;
+
+        for codec in CodecKind::all() {
+            size_bytes += get_file_size(&self.get_codec_file_path(cache_item_path, name, *codec)?)?;
+        }
+
+        Ok(Some(size_bytes).filter(|size_bytes| *size_bytes > 0))
+    }
+
+    /// Evict least recently used items if namespace or instance limits are exceeded.
+    /// Namespace policy limits replace namespace limits set with `FileCacheServiceBuilder::namespace_limits`.
+    ///
+    /// Item `stored_name` (just stored, `stored` size on disk) is never evicted.
+    /// Cache is scanned only if usage estimate exceeds limits or is outdated, see `UsageEstimates`.
+    /// Must be called without namespace lock: instance limits require locks of other namespaces.
+    pub(super) fn enforce_limits(&self, namespace: &str, namespace_dirname: &str, stored_name: &str,
+                                 stored: &StoredSize, policy: &NamespacePolicy) -> EmptyResult {
+        let namespace_limits = if policy.limits.is_unlimited() {
+            self.namespace_limits.get(namespace)
+        } else {
+            Some(&policy.limits)
+        };
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        if let Some(limits) = namespace_limits {
+            let scope = Some(namespace_dirname);
+
+            if !limits.is_unlimited() && self.usage_estimates.add_stored(scope, stored, limits, now_unixtime) {
+                let usages = self.get_namespace_usage(namespace_dirname)?;
+                let (total_bytes, entries) = self.evict(usages, limits, namespace_dirname, stored_name)?;
+                self.usage_estimates.set_scanned(scope, total_bytes, entries, now_unixtime);
+            }
+        }
+
+        if !self.limits.is_unlimited() && self.usage_estimates.add_stored(None, stored, &self.limits, now_unixtime) {
+            let mut usages = Vec::new();
+
+            for dirname in self.list_namespace_dirnames()? {
+                usages.extend(self.get_namespace_usage(&dirname)?);
+            }
+
+            let (total_bytes, entries) = self.evict(usages, &self.limits, namespace_dirname, stored_name)?;
+            self.usage_estimates.set_scanned(None, total_bytes, entries, now_unixtime);
+        }
+
+        Ok(())
+    }
+
+    fn get_namespace_usage(&self, namespace_dirname: &str) -> OperationResult<Vec<CacheEntryUsage>> {
+        let cache_item_path = self.get_cache_item_path(namespace_dirname);
+
+        let mut usages = Vec::new();
+
+        for name in self.list_item_names(&cache_item_path)? {
+            let metadata = match self.read_metadata(&cache_item_path, &name) {
+                Ok(Some(metadata)) => metadata,
+                // Removed meanwhile or corrupted, corrupted items are cleaned up by `get`
+                Ok(None) | Err(FileCacheError::Corrupted { .. }) => continue,
+                Err(e) => return Err(e)
+            };
+
+            let entry_file_path = self.get_entry_file_path(&cache_item_path, &name)?;
+            let metadata_file_path = self.get_metadata_file_path(&cache_item_path, &name)?;
+            let file_path = self.get_codec_file_path(&cache_item_path, &name, metadata.codec)?;
+
+            usages.push(CacheEntryUsage {
+                namespace_dirname: namespace_dirname.to_string(),
+                size_bytes: get_file_size(&entry_file_path)? + get_file_size(&metadata_file_path)? +
+                            get_file_size(&file_path)?,
+                created_unixtime: metadata.created_unixtime,
+                last_access_unixtime: metadata.get_last_access_unixtime(),
+                name,
+            });
+        }
+
+        Ok(usages)
+    }
+
+    /// Returns usage after eviction: total bytes and items count
+    fn evict(&self, mut usages: Vec<CacheEntryUsage>, limits: &CacheLimits,
+             stored_namespace_dirname: &str, stored_name: &str) -> OperationResult<(u64, usize)> {
+        let mut total_bytes: u64 = usages.iter().map(|usage| usage.size_bytes).sum();
+        let mut entries = usages.len();
+
+        if !limits.is_exceeded(total_bytes, entries) {
+            return Ok((total_bytes, entries))
+        }
+
+        info!("cache limits exceeded ({} bytes, {} items), least recently used items will be evicted",
+            total_bytes, entries);
+
+        usages.sort_by_key(|usage| (usage.last_access_unixtime, usage.created_unixtime));
+
+        for usage in usages {
+            if !limits.is_exceeded(total_bytes, entries) {
+                break
+            }
+
+            if usage.namespace_dirname == stored_namespace_dirname && usage.name == stored_name {
+                continue
+            }
+
+            if self.evict_entry(&usage)? {
+                info!("item '{}' has been evicted from file cache", usage.name);
+
+                total_bytes = total_bytes.saturating_sub(usage.size_bytes);
+                entries -= 1;
+            }
+        }
+
+        if limits.is_exceeded(total_bytes, entries) {
+            warn!("cache limits are still exceeded ({} bytes, {} items)", total_bytes, entries);
+        }
+
+        Ok((total_bytes, entries))
+    }
+
+    /// Remove item if it wasn't accessed or rewritten since usage scan
+    fn evict_entry(&self, usage: &CacheEntryUsage) -> OperationResult<bool> {
+        let _lock = self.lock_namespace_exclusive(&usage.namespace_dirname)?;
+
+        let cache_item_path = self.get_cache_item_path(&usage.namespace_dirname);
+
+        match self.read_metadata(&cache_item_path, &usage.name) {
+            Ok(Some(metadata)) => {
+                if metadata.created_unixtime == usage.created_unixtime &&
+                   metadata.get_last_access_unixtime() == usage.last_access_unixtime {
+                    self.remove_cache_entry_files(&cache_item_path, &usage.name)
+
+                } else {
+                    debug!("item '{}' has been changed since scan, skip eviction", usage.name);
+                    Ok(false)
+                }
+            }
+            Ok(None) | Err(FileCacheError::Corrupted { .. }) => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod eviction_tests {
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::expiry::Expiry;
+    use crate::limits::CacheLimits;
+    use crate::service::FileCacheService;
+    use crate::service::eviction::{StoredSize, UsageEstimates};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    #[test]
+    fn least_recently_used_item_should_be_evicted_when_max_entries_exceeded() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let limits = CacheLimits { max_total_bytes: None, max_entries: Some(2) };
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .limits(limits).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let other_namespace = get_random_nonblank_string();
+
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+        let third_name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &first_name, &get_demo_entity(), Expiry::Never).is_ok());
+        clock.advance(Duration::from_millis(1100));
+
+        assert!(service.store(&other_namespace, &second_name, &get_demo_entity(), Expiry::Never).is_ok());
+        clock.advance(Duration::from_millis(1100));
+
+        assert!(service.get::<Demo>(&namespace, &first_name).unwrap().is_some());
+        clock.advance(Duration::from_millis(1100));
+
+        assert!(service.store(&namespace, &third_name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert!(service.get::<Demo>(&namespace, &first_name).unwrap().is_some());
+        assert!(service.get::<Demo>(&other_namespace, &second_name).unwrap().is_none());
+        assert!(service.get::<Demo>(&namespace, &third_name).unwrap().is_some());
+    }
+
+    #[test]
+    fn items_should_be_evicted_when_max_total_bytes_exceeded() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let limits = CacheLimits { max_total_bytes: Some(1), max_entries: None };
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .limits(limits).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &first_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &second_name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert!(service.get::<Demo>(&namespace, &first_name).unwrap().is_none());
+
+        // Just stored item is kept even if it exceeds limits alone
+        assert!(service.get::<Demo>(&namespace, &second_name).unwrap().is_some());
+    }
+
+    #[test]
+    fn namespace_limits_should_not_affect_other_namespaces() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let namespace = get_random_nonblank_string();
+        let other_namespace = get_random_nonblank_string();
+
+        let limits = CacheLimits { max_total_bytes: None, max_entries: Some(1) };
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                        .namespace_limits(&namespace, limits).build().unwrap();
+
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+
+        assert!(service.store(&other_namespace, &first_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.store(&other_namespace, &second_name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert!(service.store(&namespace, &first_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &second_name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert!(service.get::<Demo>(&other_namespace, &first_name).unwrap().is_some());
+        assert!(service.get::<Demo>(&other_namespace, &second_name).unwrap().is_some());
+
+        assert!(service.get::<Demo>(&namespace, &first_name).unwrap().is_none());
+        assert!(service.get::<Demo>(&namespace, &second_name).unwrap().is_some());
+    }
+
+    #[test]
+    fn items_stored_by_another_process_should_be_accounted_after_rescan_interval() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let limits = CacheLimits { max_total_bytes: None, max_entries: Some(2) };
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .limits(limits).clock(clock.clone()).build().unwrap();
+
+        // Doesn't share usage estimates, as service of another process
+        let other_service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &get_random_nonblank_string(), &get_demo_entity(), Expiry::Never).is_ok());
+
+        for _ in 0..2 {
+            assert!(other_service.store(&namespace, &get_random_nonblank_string(), &get_demo_entity(), Expiry::Never).is_ok());
+        }
+
+        // Estimate is within limits: no scan
+        assert!(service.store(&namespace, &get_random_nonblank_string(), &get_demo_entity(), Expiry::Never).is_ok());
+        assert_eq!(service.keys(&namespace).unwrap().len(), 4);
+
+        clock.advance(Duration::from_secs(61));
+
+        let name = get_random_nonblank_string();
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert_eq!(service.keys(&namespace).unwrap().len(), 2);
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+    }
+
+    #[test]
+    fn overwritten_item_should_be_accounted_by_size_change() {
+        let estimates = UsageEstimates::default();
+
+        let limits = CacheLimits { max_total_bytes: Some(150), max_entries: Some(1) };
+
+        estimates.set_scanned(None, 100, 1, 0);
+
+        let overwrite = StoredSize { bytes: 120, replaced_bytes: Some(100) };
+        assert!(!estimates.add_stored(None, &overwrite, &limits, 0));
+
+        // 120 + 40 bytes
+        let overwrite = StoredSize { bytes: 140, replaced_bytes: Some(100) };
+        assert!(estimates.add_stored(None, &overwrite, &limits, 0));
+
+        estimates.set_scanned(None, 100, 1, 0);
+
+        let new_item = StoredSize { bytes: 10, replaced_bytes: None };
+        assert!(estimates.add_stored(None, &new_item, &limits, 0));
+    }
+}
@@ -0,0 +1,279 @@
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use log::error;
+use non_blank_string_rs::NonBlankString;
+use serde::de::DeserializeOwned;
+
+use crate::error::FileCacheError;
+use crate::key::{decode_key, encode_key};
+use crate::listing::ListOptions;
+use crate::service::{CacheEntryState, FileCacheItemMetadata, FileCacheService};
+use crate::types::{OperationResult, OptionalResult};
+
+/// Lazy iterator over namespace items, see `FileCacheService::iter`.
+///
+/// Item names are listed when iterator is created, each item is read on `next` under
+/// shared namespace lock. Items removed in between are skipped, items stored in between aren't yielded.
+pub struct CacheItems<T> {
+    service: FileCacheService,
+
+    namespace_dirname: String,
+
+    cache_item_path: PathBuf,
+
+    names: vec::IntoIter<String>,
+
+    options: ListOptions,
+
+    item_type: PhantomData<fn() -> T>,
+}
+
+impl FileCacheService {
+    /// Names of instance namespaces, sorted
+    pub fn list_namespaces(&self) -> OperationResult<Vec<String>> {
+        let mut namespaces: Vec<String> = self.list_namespace_dirnames()?.iter()
+            .filter_map(|dirname| decode_key(dirname))
+            .collect();
+
+        namespaces.sort();
+
+        Ok(namespaces)
+    }
+
+    /// Names of not expired items in `namespace`, sorted.
+    ///
+    /// Corrupted items and orphaned companion files are skipped.
+    pub fn keys(&self, namespace: &NonBlankString) -> OperationResult<Vec<String>> {
+        self.keys_with(namespace, &ListOptions::default())
+    }
+
+    /// Same as `keys`, expired items are included with `ListOptions::include_expired`
+    pub fn keys_with(&self, namespace: &NonBlankString, options: &ListOptions) -> OperationResult<Vec<String>> {
+        self.list_item_names_with(&encode_key(namespace.as_ref())?, options)
+    }
+
+    /// Lazy iterator over not expired items of `namespace`: `(name, item, metadata)`, sorted by name.
+    ///
+    /// Corrupted items, items of another type and orphaned companion files are skipped (and logged),
+    /// nothing is removed.
+    pub fn iter<T: DeserializeOwned>(&self, namespace: &NonBlankString) -> OperationResult<CacheItems<T>> {
+        self.iter_with(namespace, &ListOptions::default())
+    }
+
+    /// Same as `iter`, expired items are included with `ListOptions::include_expired`
+    pub fn iter_with<T: DeserializeOwned>(&self, namespace: &NonBlankString,
+                                          options: &ListOptions) -> OperationResult<CacheItems<T>> {
+        self.iter_items(namespace.as_ref(), options)
+    }
+
+    pub(super) fn iter_items<T: DeserializeOwned>(&self, namespace: &str,
+                                                  options: &ListOptions) -> OperationResult<CacheItems<T>> {
+        let namespace_dirname = encode_key(namespace)?;
+        let cache_item_path = self.get_cache_item_path(&namespace_dirname);
+
+        let mut names = self.list_item_names(&cache_item_path)?;
+        names.sort();
+
+        Ok(
+            CacheItems {
+                service: self.clone(),
+                namespace_dirname,
+                cache_item_path,
+                names: names.into_iter(),
+                options: *options,
+                item_type: PhantomData,
+            }
+        )
+    }
+
+    /// Read item for listing under shared namespace lock.
+    ///
+    /// Returns `None` for removed, expired (unless included), orphaned and corrupted items.
+    fn read_listed_item<T: DeserializeOwned>(&self, namespace_dirname: &str, cache_item_path: &Path, name: &str,
+                                             options: &ListOptions) -> OptionalResult<(T, FileCacheItemMetadata)> {
+        let _lock = self.lock_namespace_shared(namespace_dirname)?;
+
+        let metadata = match self.read_metadata(cache_item_path, name) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => return Ok(None),
+            Err(FileCacheError::Corrupted { path, reason }) => {
+                error!("corrupted metadata file '{}' is skipped: {}", path.display(), reason);
+                return Ok(None)
+            }
+            Err(e) => return Err(e)
+        };
+
+        if !options.include_expired && metadata.is_expired(self.get_now_in_unixtime_secs()?) {
+            return Ok(None)
+        }
+
+        match self.read_cache_file::<T>(cache_item_path, name, metadata)? {
+            CacheEntryState::Found { value, metadata } => Ok(Some((value, metadata))),
+            CacheEntryState::Corrupted { path, reason } | CacheEntryState::TypeMismatch { path, reason } => {
+                error!("cache file '{}' is skipped: {}", path.display(), reason);
+                Ok(None)
+            }
+            _ => Ok(None)
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for CacheItems<T> {
+    type Item = OperationResult<(String, T, FileCacheItemMetadata)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for name in self.names.by_ref() {
+            match self.service.read_listed_item::<T>(&self.namespace_dirname, &self.cache_item_path,
+                                                     &name, &self.options) {
+                Ok(Some((value, metadata))) => return Some(Ok((name, value, metadata))),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e))
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod iter_tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::expiry::Expiry;
+    use crate::listing::ListOptions;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn namespaces_should_be_listed() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        assert!(service.list_namespaces().unwrap().is_empty());
+
+        let users = NonBlankString::parse("users/v2").unwrap();
+        let dogs = NonBlankString::parse("dogs").unwrap();
+
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&users, &name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.store(&dogs, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert_eq!(service.list_namespaces().unwrap(), vec!["dogs".to_string(), "users/v2".to_string()]);
+    }
+
+    #[test]
+    fn keys_should_skip_expired_and_orphaned_items() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = NonBlankString::parse("demo").unwrap();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = NonBlankString::parse("dogs").unwrap();
+
+        let live_name = NonBlankString::parse("live").unwrap();
+        let expired_name = NonBlankString::parse("expired").unwrap();
+        let orphaned_name = NonBlankString::parse("orphaned").unwrap();
+
+        assert!(service.store(&namespace, &live_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &expired_name, &get_demo_entity(), Duration::from_secs(1)).is_ok());
+        assert!(service.store(&namespace, &orphaned_name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let orphaned_filename = format!("orphaned-{}", CACHE_FILENAME_POSTFIX);
+        fs::remove_file(root_path.join("demo").join("dogs").join(orphaned_filename)).unwrap();
+
+        clock.advance(Duration::from_secs(3));
+
+        assert_eq!(service.keys(&namespace).unwrap(), vec!["live".to_string()]);
+
+        let options = ListOptions { include_expired: true };
+        assert_eq!(service.keys_with(&namespace, &options).unwrap(), vec!["expired".to_string(), "live".to_string()]);
+
+        assert!(service.keys(&get_random_nonblank_string()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn iter_should_yield_items_with_metadata() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        let first_name = NonBlankString::parse("first").unwrap();
+        let second_name = NonBlankString::parse("second").unwrap();
+        let expired_name = NonBlankString::parse("expired").unwrap();
+
+        let first = get_demo_entity();
+        let second = get_demo_entity();
+
+        assert!(service.store(&namespace, &second_name, &second, Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &first_name, &first, Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &expired_name, &get_demo_entity(), Duration::from_secs(1)).is_ok());
+
+        // Items stored after iterator creation aren't yielded
+        let items = service.iter::<Demo>(&namespace).unwrap();
+
+        assert!(service.store(&namespace, &get_random_nonblank_string(), &get_demo_entity(), Expiry::Never).is_ok());
+
+        clock.advance(Duration::from_secs(3));
+
+        let items: Vec<(String, Demo, _)> = items.map(|item| item.unwrap()).collect();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].0.as_str(), &items[0].1), ("first", &first));
+        assert_eq!((items[1].0.as_str(), &items[1].1), ("second", &second));
+        assert!(items[0].2.get_expires_at_unixtime().is_none());
+
+        let options = ListOptions { include_expired: true };
+        assert_eq!(service.iter_with::<Demo>(&namespace, &options).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn iter_should_skip_items_of_another_type() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &get_random_nonblank_string(), &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &name, &vec![1, 2, 3], Expiry::Never).is_ok());
+
+        assert_eq!(service.iter::<Demo>(&namespace).unwrap().count(), 1);
+
+        // Nothing is removed
+        assert!(service.get::<Vec<i32>>(&namespace, &name).unwrap().is_some());
+    }
+}
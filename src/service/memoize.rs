@@ -0,0 +1,553 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use log::{debug, warn};
+use non_blank_string_rs::NonBlankString;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{FileCacheError, GetOrInsertError};
+use crate::expiry::Expiry;
+use crate::key::encode_key;
+use crate::lock::{FileLock, LOCK_FILENAME_POSTFIX};
+use crate::service::{FileCacheItemMetadata, FileCacheService};
+use crate::types::{OperationResult, OptionalResult};
+
+/// Directory for item lock files (cross-process single-flight), inside locks directory
+const ITEM_LOCKS_DIRNAME: &str = "items";
+
+impl FileCacheService {
+    /// Get item from cache, or compute it with `init` and store with `expiry`.
+    ///
+    /// Computed value is returned as is, without reading it back from cache.
+    ///
+    /// Single-flight: only one thread computes an item, other threads calling `get_or_insert_with`
+    /// for the same item wait and read stored value. See also `FileCacheServiceBuilder::cross_process_single_flight`.
+    ///
+    /// Compute time is recorded in item metadata, item can be refreshed before expiration
+    /// (see `FileCacheServiceBuilder::early_refresh`).
+    pub fn get_or_insert_with<T, F>(&self, namespace: &NonBlankString, name: &NonBlankString,
+                                    expiry: impl Into<Expiry>, init: F) -> OperationResult<T>
+        where T: Serialize + DeserializeOwned, F: FnOnce() -> T {
+        match self.get_or_try_insert_with(namespace, name, expiry, || Ok::<T, FileCacheError>(init())) {
+            Ok(value) => Ok(value),
+            Err(GetOrInsertError::Cache(e)) | Err(GetOrInsertError::Init(e)) => Err(e)
+        }
+    }
+
+    /// Same as `get_or_insert_with`, but `init` can fail. Nothing is stored on error.
+    ///
+    /// If `init` fails, expired item in grace period is returned (see `FileCacheServiceBuilder::grace_period`),
+    /// or current item if it was refreshed early.
+    pub fn get_or_try_insert_with<T, E, F>(&self, namespace: &NonBlankString, name: &NonBlankString,
+                                           expiry: impl Into<Expiry>, init: F) -> Result<T, GetOrInsertError<E>>
+        where T: Serialize + DeserializeOwned, F: FnOnce() -> Result<T, E> {
+        // Item due for early refresh and its creation time
+        let current = match self.get_entry::<T>(namespace.as_ref(), name.as_ref())? {
+            Some((value, metadata)) => {
+                if !self.is_early_refresh_due(&metadata)? {
+                    return Ok(value)
+                }
+
+                debug!("item '{}' is due for early refresh", name.as_ref());
+                Some((value, metadata.created_unixtime))
+            }
+            None => None
+        };
+
+        let namespace_dirname = encode_key(namespace.as_ref())?;
+        let item_dirname = encode_key(name.as_ref())?;
+
+        let _flight = self.single_flight.enter(format!("{}/{}", namespace_dirname, item_dirname));
+        let _item_lock = self.lock_item_flight(&namespace_dirname, &item_dirname)?;
+
+        // Value could have been stored while waiting for flight
+        if let Some((value, metadata)) = self.get_entry::<T>(namespace.as_ref(), name.as_ref())? {
+            if current.as_ref().is_none_or(|(_, created_unixtime)| metadata.created_unixtime != *created_unixtime) {
+                return Ok(value)
+            }
+        }
+
+        debug!("compute value for item '{}'", name.as_ref());
+
+        let started = Instant::now();
+
+        let value = match init() {
+            Ok(value) => value,
+            Err(e) => {
+                if let Some((value, _)) = current {
+                    warn!("early refresh has failed for item '{}', current value is returned", name.as_ref());
+                    return Ok(value)
+                }
+
+                // Stale-if-error: expired item in grace period is better than nothing
+                return match self.read_stale_item::<T>(namespace.as_ref(), name.as_ref())? {
+                    Some(value) => {
+                        warn!("value init has failed for item '{}', stale value is returned", name.as_ref());
+                        Ok(value)
+                    }
+                    None => Err(GetOrInsertError::Init(e))
+                }
+            }
+        };
+
+        let compute_time_ms = started.elapsed().as_millis() as u64;
+
+        self.store_item(namespace.as_ref(), name.as_ref(), &value, Some(expiry.into()), Some(compute_time_ms))?;
+
+        Ok(value)
+    }
+
+    /// Roll early refresh of item, see `FileCacheServiceBuilder::early_refresh`
+    pub(super) fn is_early_refresh_due(&self, metadata: &FileCacheItemMetadata) -> OperationResult<bool> {
+        match self.early_refresh_beta {
+            Some(beta) => {
+                let now_unixtime = self.get_now_in_unixtime_secs()?;
+                Ok(metadata.is_early_refresh_due(now_unixtime, beta, 1.0 - fastrand::f64()))
+            }
+            None => Ok(false)
+        }
+    }
+
+    /// Item lock for cross-process single-flight, `None` if disabled
+    fn lock_item_flight(&self, namespace_dirname: &str, item_dirname: &str) -> OptionalResult<FileLock> {
+        if !self.cross_process_single_flight {
+            return Ok(None)
+        }
+
+        loop {
+            let lock_file_path = self.get_item_lock_file_path(namespace_dirname, item_dirname)?;
+
+            match FileLock::exclusive(&lock_file_path, self.lock_timeout) {
+                Ok(lock) => return Ok(Some(lock)),
+                // Item locks directory has been removed meanwhile, see `remove_unused_item_locks`
+                Err(FileCacheError::IOError(e)) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e)
+            }
+        }
+    }
+
+    /// Remove item lock files of all namespaces which aren't held by anyone
+    pub(super) fn remove_all_unused_item_locks(&self) -> OperationResult<usize> {
+        let mut removed = 0;
+
+        for entry in self.read_dir_if_exists(&self.get_locks_path().join(ITEM_LOCKS_DIRNAME))? {
+            let entry = entry?;
+
+            if entry.file_type()?.is_dir() {
+                removed += self.remove_unused_item_locks(&entry.file_name().to_string_lossy())?;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Remove item lock files of namespace which aren't held by anyone, then namespace directory if empty.
+    /// Returns count of removed lock files.
+    ///
+    /// Process which opened lock file right before removal could still lock it,
+    /// then item could be computed twice: single-flight is best effort, stored items aren't affected.
+    pub(super) fn remove_unused_item_locks(&self, namespace_dirname: &str) -> OperationResult<usize> {
+        let item_locks_path = self.get_item_locks_path(namespace_dirname);
+
+        let mut removed = 0;
+
+        for entry in self.read_dir_if_exists(&item_locks_path)? {
+            let lock_file_path = entry?.path();
+
+            if let Some(_lock) = FileLock::try_exclusive(&lock_file_path)? {
+                if self.remove_file_if_exists(&lock_file_path)? {
+                    removed += 1;
+                }
+            }
+        }
+
+        // Directory with locks in use is kept
+        match fs::remove_dir(&item_locks_path) {
+            Ok(_) => debug!("item locks directory '{}' has been removed", item_locks_path.display()),
+            Err(e) => debug!("item locks directory '{}' is kept: {}", item_locks_path.display(), e)
+        }
+
+        Ok(removed)
+    }
+
+    /// `[INSTANCE]/.locks/items/[NAMESPACE]/[ITEM].lock`, outside of namespace directory as namespace lock
+    fn get_item_lock_file_path(&self, namespace_dirname: &str, item_dirname: &str) -> OperationResult<PathBuf> {
+        let namespace_locks_path = self.get_item_locks_path(namespace_dirname);
+
+        if !namespace_locks_path.exists() {
+            fs::create_dir_all(&namespace_locks_path)?;
+        }
+
+        Ok(namespace_locks_path.join(format!("{}.{}", item_dirname, LOCK_FILENAME_POSTFIX)))
+    }
+
+    /// Item locks live apart from namespace lock files `[NAMESPACE].lock`,
+    /// so namespace named `[OTHER NAMESPACE].lock` doesn't collide with them
+    pub(super) fn get_item_locks_path(&self, namespace_dirname: &str) -> PathBuf {
+        self.get_locks_path().join(ITEM_LOCKS_DIRNAME).join(namespace_dirname)
+    }
+}
+
+#[cfg(test)]
+mod get_or_insert_with_tests {
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::codec::CodecKind;
+    use crate::compression::Compression;
+    use crate::error::GetOrInsertError;
+    use crate::expiry::Expiry;
+    use crate::key::encode_key;
+    use crate::lock::FileLock;
+    use crate::service::{FileCacheItemMetadata, FileCacheService};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    #[test]
+    fn value_should_be_computed_once() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+        let calls = Cell::new(0);
+
+        let first = service.get_or_insert_with(&namespace, &name, Expiry::Never, || {
+            calls.set(calls.get() + 1);
+            Demo { login: demo.login.clone() }
+        }).unwrap();
+
+        let second = service.get_or_insert_with(&namespace, &name, Expiry::Never, || {
+            calls.set(calls.get() + 1);
+            get_demo_entity()
+        }).unwrap();
+
+        assert_eq!(calls.get(), 1);
+        assert_eq!(first, demo);
+        assert_eq!(second, demo);
+    }
+
+    #[test]
+    fn init_error_should_be_returned_and_nothing_stored() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let result = service.get_or_try_insert_with::<Demo, _, _>(
+            &namespace, &name, Expiry::Never, || Err("upstream is down"));
+
+        match result {
+            Err(GetOrInsertError::Init(e)) => assert_eq!(e, "upstream is down"),
+            _ => panic!("init error expected")
+        }
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn cached_value_should_be_returned_without_init_call() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        let result = service.get_or_try_insert_with::<Demo, String, _>(
+            &namespace, &name, Expiry::Never, || panic!("init shouldn't be called")).unwrap();
+
+        assert_eq!(result, demo);
+    }
+
+    #[test]
+    fn stale_value_should_be_returned_on_init_error() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .grace_period(Duration::from_secs(300))
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(60)).is_ok());
+
+        clock.advance(Duration::from_secs(120));
+
+        let result = service.get_or_try_insert_with::<Demo, _, _>(
+            &namespace, &name, Duration::from_secs(60), || Err("upstream is down")).unwrap();
+
+        assert_eq!(result, demo);
+
+        clock.advance(Duration::from_secs(300));
+
+        let result = service.get_or_try_insert_with::<Demo, _, _>(
+            &namespace, &name, Duration::from_secs(60), || Err("upstream is down"));
+
+        assert!(matches!(result, Err(GetOrInsertError::Init(_))));
+    }
+
+    #[test]
+    fn value_should_be_computed_once_by_concurrent_threads() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+        let calls = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let value = service.get_or_insert_with(&namespace, &name, Expiry::Never, || {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(50));
+                        Demo { login: demo.login.clone() }
+                    }).unwrap();
+
+                    assert_eq!(value, demo);
+                });
+            }
+        });
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn value_should_be_computed_once_across_processes() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let calls = AtomicUsize::new(0);
+
+        // Services don't share in-process flights, as services of different processes
+        let services: Vec<FileCacheService> = (0..4).map(|_|
+            FileCacheService::builder(&root_path_str, &instance_name)
+                .cross_process_single_flight(true)
+                .build().unwrap()
+        ).collect();
+
+        thread::scope(|scope| {
+            for service in &services {
+                scope.spawn(|| {
+                    service.get_or_insert_with(&namespace, &name, Expiry::Never, || {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(50));
+                        get_demo_entity()
+                    }).unwrap();
+                });
+            }
+        });
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn item_locks_should_not_collide_with_namespace_locks() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .cross_process_single_flight(true)
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        // Namespace lock file `[NAMESPACE].lock` is created
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let lock_namespace = NonBlankString::parse(&format!("{}.lock", namespace.as_ref())).unwrap();
+
+        assert!(service.get_or_insert_with(&lock_namespace, &name, Expiry::Never, get_demo_entity).is_ok());
+        assert!(service.get::<Demo>(&lock_namespace, &name).unwrap().is_some());
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+    }
+
+    #[test]
+    fn unused_item_locks_should_be_removed() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .cross_process_single_flight(true)
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+        let held_name = get_random_nonblank_string();
+
+        let namespace_dirname = encode_key(namespace.as_ref()).unwrap();
+
+        assert!(service.get_or_insert_with(&namespace, &name, Expiry::Never, get_demo_entity).is_ok());
+
+        let lock_file_path = service.get_item_lock_file_path(&namespace_dirname, &encode_key(name.as_ref()).unwrap()).unwrap();
+        assert!(lock_file_path.exists());
+
+        // Lock of another process computing item right now
+        let held_lock_file_path = service.get_item_lock_file_path(
+            &namespace_dirname, &encode_key(held_name.as_ref()).unwrap()).unwrap();
+        let held_lock = FileLock::exclusive(&held_lock_file_path, None).unwrap();
+
+        assert!(service.purge_expired().is_ok());
+
+        assert!(!lock_file_path.exists());
+        assert!(held_lock_file_path.exists());
+
+        drop(held_lock);
+
+        assert!(service.invalidate_namespace(&namespace).is_ok());
+        assert!(!service.get_item_locks_path(&namespace_dirname).exists());
+
+        // Directory is created again for next flight
+        assert!(service.get_or_insert_with(&namespace, &name, Expiry::Never, get_demo_entity).is_ok());
+        assert!(lock_file_path.exists());
+    }
+
+    #[test]
+    fn early_refresh_should_be_due_closer_to_expiration() {
+        let metadata = FileCacheItemMetadata {
+            ttl_secs: 1000,
+            created_unixtime: 0,
+            expires_at: Some(1000),
+            relative_expiry: true,
+            codec: CodecKind::Json,
+            compression: Compression::None,
+            last_access_unixtime: None,
+            grace_period_secs: None,
+            compute_time_ms: Some(2000),
+        };
+
+        // Refresh window: 2 secs * 1.0 * -ln(0.5) = 1.386 secs
+        assert!(!metadata.is_early_refresh_due(998, 1.0, 0.5));
+        assert!(metadata.is_early_refresh_due(999, 1.0, 0.5));
+
+        assert!(!metadata.is_early_refresh_due(999, 1.0, 1.0));
+        assert!(metadata.is_early_refresh_due(990, 10.0, 0.5));
+
+        let metadata = FileCacheItemMetadata { compute_time_ms: None, ..metadata };
+        assert!(!metadata.is_early_refresh_due(999, 1.0, 0.01));
+    }
+
+    #[test]
+    fn value_should_be_refreshed_before_expiration() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        // Huge beta: refresh is almost certainly due in the last second
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .early_refresh(1e12)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let calls = Cell::new(0);
+
+        let init = || {
+            calls.set(calls.get() + 1);
+            thread::sleep(Duration::from_millis(5));
+            Ok::<Demo, String>(get_demo_entity())
+        };
+
+        let first = service.get_or_try_insert_with(&namespace, &name, Duration::from_secs(60), init).unwrap();
+
+        clock.advance(Duration::from_secs(59));
+
+        let second = service.get_or_try_insert_with(&namespace, &name, Duration::from_secs(60), init).unwrap();
+
+        assert_eq!(calls.get(), 2);
+        assert_ne!(first, second);
+
+        // Current value is returned if early refresh fails
+        clock.advance(Duration::from_secs(59));
+
+        let result = service.get_or_try_insert_with::<Demo, _, _>(
+            &namespace, &name, Duration::from_secs(60), || Err("upstream is down")).unwrap();
+
+        assert_eq!(result, second);
+    }
+}
@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use log::{error, info};
+use non_blank_string_rs::NonBlankString;
+use serde::de::DeserializeOwned;
+
+use crate::corruption::CorruptionPolicy;
+use crate::entry::CacheEntryInfo;
+use crate::error::FileCacheError;
+use crate::key::encode_key;
+use crate::service::{FileCacheItemMetadata, FileCacheService};
+use crate::types::{OperationResult, OptionalResult};
+
+impl FileCacheService {
+    /// Details of item with cache `name` in `namespace`: created time, TTL, remaining TTL, payload size and codec.
+    ///
+    /// Payload isn't read. Returns `None` for missing, expired and orphaned items,
+    /// they aren't removed (see `get`). Corrupted metadata is reported as `None`
+    /// or as `FileCacheError::Corrupted` with `CorruptionPolicy::Error`.
+    pub fn metadata(&self, namespace: &NonBlankString, name: &NonBlankString) -> OptionalResult<CacheEntryInfo> {
+        info!("get entity metadata from file cache: namespace='{}', item_name='{}'", namespace.as_ref(), name.as_ref());
+
+        let namespace_dirname = encode_key(namespace.as_ref())?;
+        let cache_item_path = self.get_cache_item_path(&namespace_dirname);
+
+        if !cache_item_path.exists() {
+            return Ok(None)
+        }
+
+        let _lock = self.lock_namespace_shared(&namespace_dirname)?;
+
+        let metadata = match self.read_metadata(&cache_item_path, name.as_ref()) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => return Ok(None),
+            Err(FileCacheError::Corrupted { path, reason }) if self.corruption_policy != CorruptionPolicy::Error => {
+                error!("corrupted metadata file '{}': {}", path.display(), reason);
+                return Ok(None)
+            }
+            Err(e) => return Err(e)
+        };
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        if metadata.is_expired(now_unixtime) {
+            return Ok(None)
+        }
+
+        match self.get_payload_size(&cache_item_path, name.as_ref(), metadata.codec)? {
+            Some(payload_size) => Ok(Some(CacheEntryInfo::new(&metadata, payload_size, now_unixtime))),
+            None => Ok(None)
+        }
+    }
+
+    /// Same as `get`, item is returned along with its details (see `metadata`)
+    pub fn get_with_metadata<T: DeserializeOwned>(&self, namespace: &NonBlankString,
+                                                  name: &NonBlankString) -> OptionalResult<(T, CacheEntryInfo)> {
+        match self.get_entry::<T>(namespace.as_ref(), name.as_ref())? {
+            Some((value, metadata)) => {
+                let cache_item_path = self.get_cache_item_path(&encode_key(namespace.as_ref())?);
+                let info = self.get_entry_info(&cache_item_path, name.as_ref(), &metadata)?;
+                Ok(Some((value, info)))
+            }
+            None => Ok(None)
+        }
+    }
+
+    fn get_entry_info(&self, cache_item_path: &Path, name: &str,
+                      metadata: &FileCacheItemMetadata) -> OperationResult<CacheEntryInfo> {
+        let payload_size = self.get_payload_size(cache_item_path, name, metadata.codec)?.unwrap_or(0);
+
+        Ok(CacheEntryInfo::new(metadata, payload_size, self.get_now_in_unixtime_secs()?))
+    }
+}
+
+#[cfg(test)]
+mod metadata_tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::codec::CodecKind;
+    use crate::compression::Compression;
+    use crate::corruption::CorruptionPolicy;
+    use crate::error::FileCacheError;
+    use crate::expiry::Expiry;
+    use crate::service::{FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn metadata_should_return_remaining_ttl() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.metadata(&namespace, &name).unwrap().is_none());
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+
+        clock.advance(Duration::from_secs(20));
+
+        let info = service.metadata(&namespace, &name).unwrap().unwrap();
+
+        assert_eq!(info.ttl, Some(Duration::from_secs(60)));
+        assert_eq!(info.remaining_ttl, Some(Duration::from_secs(40)));
+        assert_eq!(info.codec, CodecKind::Json);
+        assert_eq!(info.compression, Compression::None);
+        assert!(info.payload_size > 0);
+
+        clock.advance(Duration::from_secs(60));
+
+        assert!(service.metadata(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn get_with_metadata_should_return_item_and_details() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Expiry::Never).is_ok());
+
+        let (value, info) = service.get_with_metadata::<Demo>(&namespace, &name).unwrap().unwrap();
+
+        assert_eq!(value, demo);
+        assert!(info.remaining_ttl.is_none());
+        assert_eq!(Some(info), service.metadata(&namespace, &name).unwrap());
+
+        assert!(service.get_with_metadata::<Demo>(&namespace, &get_random_nonblank_string()).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupted_metadata_should_be_reported_according_to_policy() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        fs::write(root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(metadata_filename), "{").unwrap();
+
+        assert!(service.metadata(&namespace, &name).unwrap().is_none());
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .corruption_policy(CorruptionPolicy::Error).build().unwrap();
+
+        assert!(matches!(service.metadata(&namespace, &name), Err(FileCacheError::Corrupted { .. })));
+    }
+}
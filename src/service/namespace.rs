@@ -0,0 +1,194 @@
+use std::marker::PhantomData;
+
+use non_blank_string_rs::NonBlankString;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::expiry::Expiry;
+use crate::key::encode_key;
+use crate::listing::ListOptions;
+use crate::lookup::CacheLookup;
+use crate::service::{CacheItems, FileCacheService};
+use crate::types::{EmptyResult, OperationResult, OptionalResult};
+
+/// Typed handle of cache namespace, see `FileCacheService::namespace`.
+///
+/// Item type is fixed at compile time: items of another type can't be stored into namespace
+/// or read from it with the handle.
+pub struct Namespace<T> {
+    service: FileCacheService,
+
+    name: String,
+
+    item_type: PhantomData<fn() -> T>,
+}
+
+impl FileCacheService {
+    /// Typed handle of `namespace` with items of type `T`
+    pub fn namespace<T>(&self, namespace: &NonBlankString) -> OperationResult<Namespace<T>>
+        where T: Serialize + DeserializeOwned {
+        encode_key(namespace.as_ref())?;
+
+        Ok(
+            Namespace {
+                service: self.clone(),
+                name: namespace.as_ref().to_string(),
+                item_type: PhantomData,
+            }
+        )
+    }
+}
+
+impl<T> Namespace<T> where T: Serialize + DeserializeOwned {
+    /// Namespace name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Same as `FileCacheService::get`
+    pub fn get(&self, name: &NonBlankString) -> OptionalResult<T> {
+        self.service.get_item(&self.name, name.as_ref())
+    }
+
+    /// Same as `FileCacheService::get_detailed`
+    pub fn get_detailed(&self, name: &NonBlankString) -> OperationResult<CacheLookup<T>> {
+        self.service.lookup_item(&self.name, name.as_ref())
+    }
+
+    /// Same as `FileCacheService::store`
+    pub fn store(&self, name: &NonBlankString, item: &T, expiry: impl Into<Expiry>) -> EmptyResult {
+        self.service.store_item(&self.name, name.as_ref(), item, Some(expiry.into()), None)
+    }
+
+    /// Same as `FileCacheService::store_default`
+    pub fn store_default(&self, name: &NonBlankString, item: &T) -> EmptyResult {
+        self.service.store_item(&self.name, name.as_ref(), item, None, None)
+    }
+
+    /// Same as `FileCacheService::remove`
+    pub fn remove(&self, name: &NonBlankString) -> OperationResult<bool> {
+        self.service.remove_item(&self.name, name.as_ref())
+    }
+
+    /// Same as `FileCacheService::keys`
+    pub fn keys(&self) -> OperationResult<Vec<String>> {
+        self.service.list_item_names_with(&encode_key(&self.name)?, &ListOptions::default())
+    }
+
+    /// Same as `FileCacheService::iter`
+    pub fn iter(&self) -> OperationResult<CacheItems<T>> {
+        self.service.iter_items(&self.name, &ListOptions::default())
+    }
+
+    /// Remove all items, same as `FileCacheService::invalidate_namespace`
+    pub fn clear(&self) -> EmptyResult {
+        self.service.clear_namespace(&self.name)
+    }
+}
+
+impl<T> Clone for Namespace<T> {
+    fn clone(&self) -> Self {
+        Namespace {
+            service: self.service.clone(),
+            name: self.name.clone(),
+            item_type: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod namespace_tests {
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::expiry::Expiry;
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn items_should_be_stored_and_retrieved_with_handle() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demos = service.namespace::<Demo>(&namespace).unwrap();
+
+        let demo = get_demo_entity();
+
+        assert!(demos.store(&name, &demo, Expiry::Never).is_ok());
+
+        assert_eq!(demos.get(&name).unwrap(), Some(demo));
+
+        // Same layout as service API
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        assert!(demos.remove(&name).unwrap());
+        assert!(demos.get(&name).unwrap().is_none());
+    }
+
+    #[test]
+    fn keys_should_return_not_expired_items() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        let demos = service.namespace::<Demo>(&namespace).unwrap();
+
+        assert!(demos.keys().unwrap().is_empty());
+
+        let first_name = NonBlankString::parse("first/1").unwrap();
+        let second_name = NonBlankString::parse("second").unwrap();
+        let expired_name = NonBlankString::parse("expired").unwrap();
+
+        assert!(demos.store(&second_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(demos.store(&first_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(demos.store(&expired_name, &get_demo_entity(), Duration::from_secs(1)).is_ok());
+
+        clock.advance(Duration::from_secs(3));
+
+        assert_eq!(demos.keys().unwrap(), vec!["first/1".to_string(), "second".to_string()]);
+    }
+
+    #[test]
+    fn clear_should_remove_all_items() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demos = service.namespace::<Demo>(&namespace).unwrap();
+
+        assert!(demos.store(&name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert!(demos.clear().is_ok());
+
+        assert!(demos.keys().unwrap().is_empty());
+        assert!(demos.get(&name).unwrap().is_none());
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use log::{info, warn};
+use non_blank_string_rs::NonBlankString;
+
+use crate::error::FileCacheError;
+use crate::file::write_file_atomically;
+use crate::key::encode_key;
+use crate::memory::FileStamp;
+use crate::policy::NamespacePolicy;
+use crate::service::FileCacheService;
+use crate::types::{EmptyResult, OperationResult, OptionalResult};
+
+/// Directory for namespace policy files, inside instance directory
+pub const POLICIES_DIRNAME: &str = ".policies";
+
+/// Parsed namespace policies, shared by clones, so `get` and `store` don't read policy file each time.
+/// Key - namespace directory name.
+///
+/// Entry is valid while policy file has the same `FileStamp`, so policies set by other processes are detected.
+#[derive(Clone, Default)]
+pub(super) struct PolicyCache {
+    policies: Arc<Mutex<HashMap<String, CachedPolicy>>>,
+}
+
+struct CachedPolicy {
+    /// `None` - policy file doesn't exist
+    stamp: Option<FileStamp>,
+
+    policy: Option<NamespacePolicy>,
+}
+
+impl PolicyCache {
+    /// Cached policy, outer `None` if missing or policy file has changed
+    pub(super) fn get(&self, namespace_dirname: &str, stamp: &Option<FileStamp>) -> Option<Option<NamespacePolicy>> {
+        self.lock_policies().get(namespace_dirname)
+            .filter(|cached| cached.stamp == *stamp)
+            .map(|cached| cached.policy)
+    }
+
+    pub(super) fn insert(&self, namespace_dirname: &str, stamp: Option<FileStamp>, policy: Option<NamespacePolicy>) {
+        self.lock_policies().insert(namespace_dirname.to_string(), CachedPolicy { stamp, policy });
+    }
+
+    fn lock_policies(&self) -> MutexGuard<'_, HashMap<String, CachedPolicy>> {
+        self.policies.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl FileCacheService {
+    /// Register `policy` for `namespace`: default expiry for `store_default`, limits, codec and compression.
+    ///
+    /// Policy is persisted in `[INSTANCE NAME]/.policies/[NAMESPACE].json`,
+    /// so other processes sharing cache root pick it up.
+    /// Returns `FileCacheError::InvalidConfig` for invalid policy.
+    pub fn set_namespace_policy(&self, namespace: &NonBlankString, policy: &NamespacePolicy) -> EmptyResult {
+        info!("set file cache namespace '{}' policy: {:?}", namespace.as_ref(), policy);
+        policy.validate()?;
+
+        let policy_file_path = self.get_policy_file_path(&encode_key(namespace.as_ref())?);
+
+        if let Some(policies_path) = policy_file_path.parent() {
+            fs::create_dir_all(policies_path)?;
+        }
+
+        let policy_json = serde_json::to_string(policy)?;
+        write_file_atomically(&policy_file_path, policy_json.as_bytes(), &self.write_options)
+    }
+
+    /// Policy registered for `namespace`, `None` if policy file is malformed or invalid
+    pub fn get_namespace_policy(&self, namespace: &NonBlankString) -> OptionalResult<NamespacePolicy> {
+        self.read_namespace_policy(&encode_key(namespace.as_ref())?)
+    }
+
+    /// Remove policy of `namespace`, instance settings are used then.
+    ///
+    /// Returns `true` if policy existed.
+    pub fn remove_namespace_policy(&self, namespace: &NonBlankString) -> OperationResult<bool> {
+        info!("remove file cache namespace '{}' policy", namespace.as_ref());
+        let policy_file_path = self.get_policy_file_path(&encode_key(namespace.as_ref())?);
+        self.remove_file_if_exists(&policy_file_path)
+    }
+
+    /// Malformed or invalid policy file is ignored with warning, instance settings are used then
+    pub(super) fn read_namespace_policy(&self, namespace_dirname: &str) -> OptionalResult<NamespacePolicy> {
+        let policy_file_path = self.get_policy_file_path(namespace_dirname);
+        let stamp = FileStamp::read(&policy_file_path)?;
+
+        if let Some(policy) = self.policy_cache.get(namespace_dirname, &stamp) {
+            return Ok(policy)
+        }
+
+        let policy = match self.read_file_if_exists(&policy_file_path)? {
+            Some(policy_json) => parse_namespace_policy(&policy_file_path, &policy_json),
+            None => None
+        };
+
+        self.policy_cache.insert(namespace_dirname, stamp, policy);
+        Ok(policy)
+    }
+
+    pub(super) fn get_policy_file_path(&self, namespace_dirname: &str) -> PathBuf {
+        self.instance_path.join(POLICIES_DIRNAME).join(format!("{}.json", namespace_dirname))
+    }
+}
+
+/// Returns `None` for malformed or invalid policy: one bad file shouldn't break namespace
+pub(super) fn parse_namespace_policy(policy_file_path: &Path, policy_json: &[u8]) -> Option<NamespacePolicy> {
+    let result = serde_json::from_slice::<NamespacePolicy>(policy_json)
+        .map_err(FileCacheError::from)
+        .and_then(|policy| policy.validate().map(|_| policy));
+
+    match result {
+        Ok(policy) => Some(policy),
+        Err(e) => {
+            warn!("namespace policy file '{}' is ignored, instance settings are used: {}", policy_file_path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod namespace_policy_tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::error::FileCacheError;
+    use crate::expiry::Expiry;
+    use crate::limits::CacheLimits;
+    use crate::policy::NamespacePolicy;
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn store_default_should_use_namespace_ttl() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .default_ttl(Duration::from_secs(3600))
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let tokens = get_random_nonblank_string();
+        let catalog = get_random_nonblank_string();
+
+        let policy = NamespacePolicy { default_ttl_secs: Some(300), ..NamespacePolicy::default() };
+        assert!(service.set_namespace_policy(&tokens, &policy).is_ok());
+
+        let name = get_random_nonblank_string();
+
+        assert!(service.store_default(&tokens, &name, &get_demo_entity()).is_ok());
+        assert!(service.store_default(&catalog, &name, &get_demo_entity()).is_ok());
+
+        clock.advance(Duration::from_secs(600));
+
+        assert!(service.get::<Demo>(&tokens, &name).unwrap().is_none());
+        assert!(service.get::<Demo>(&catalog, &name).unwrap().is_some());
+    }
+
+    #[test]
+    fn policy_should_be_shared_between_services() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+        let other_service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        let policy = NamespacePolicy {
+            default_ttl_secs: Some(300),
+            limits: CacheLimits { max_total_bytes: None, max_entries: Some(1) },
+            ..NamespacePolicy::default()
+        };
+
+        assert!(service.set_namespace_policy(&namespace, &policy).is_ok());
+
+        assert_eq!(other_service.get_namespace_policy(&namespace).unwrap(), Some(policy));
+
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+
+        assert!(other_service.store(&namespace, &first_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(other_service.store(&namespace, &second_name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert!(other_service.get::<Demo>(&namespace, &first_name).unwrap().is_none());
+        assert!(other_service.get::<Demo>(&namespace, &second_name).unwrap().is_some());
+
+        assert!(service.remove_namespace_policy(&namespace).unwrap());
+        assert!(other_service.get_namespace_policy(&namespace).unwrap().is_none());
+    }
+
+    #[test]
+    fn cached_policy_should_be_refreshed_when_changed_by_another_service() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+        let other_service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        assert!(service.get_namespace_policy(&namespace).unwrap().is_none());
+
+        let policy = NamespacePolicy { default_ttl_secs: Some(300), ..NamespacePolicy::default() };
+        assert!(other_service.set_namespace_policy(&namespace, &policy).is_ok());
+        assert_eq!(service.get_namespace_policy(&namespace).unwrap(), Some(policy));
+
+        let policy = NamespacePolicy { default_ttl_secs: Some(3600), ..NamespacePolicy::default() };
+        assert!(other_service.set_namespace_policy(&namespace, &policy).is_ok());
+        assert_eq!(service.get_namespace_policy(&namespace).unwrap(), Some(policy));
+
+        assert!(other_service.remove_namespace_policy(&namespace).unwrap());
+        assert!(service.get_namespace_policy(&namespace).unwrap().is_none());
+    }
+
+    #[test]
+    fn policy_should_be_kept_on_invalidation() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        let policy = NamespacePolicy { default_ttl_secs: Some(300), ..NamespacePolicy::default() };
+        assert!(service.set_namespace_policy(&namespace, &policy).is_ok());
+
+        assert!(service.invalidate_namespace(&namespace).is_ok());
+        assert!(service.invalidate_instance().is_ok());
+
+        assert_eq!(service.get_namespace_policy(&namespace).unwrap(), Some(policy));
+    }
+
+    #[test]
+    fn invalid_policy_should_be_rejected() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        let policy = NamespacePolicy {
+            limits: CacheLimits { max_total_bytes: Some(0), max_entries: None },
+            ..NamespacePolicy::default()
+        };
+
+        assert!(matches!(service.set_namespace_policy(&namespace, &policy), Err(FileCacheError::InvalidConfig(_))));
+        assert!(service.get_namespace_policy(&namespace).unwrap().is_none());
+    }
+
+    #[test]
+    fn bad_policy_file_should_fall_back_to_instance_settings() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let policy_file_path = service.get_policy_file_path(namespace.as_ref());
+        fs::create_dir_all(policy_file_path.parent().unwrap()).unwrap();
+
+        for policy_json in ["{invalid-json", r#"{"default_ttl_secs": 0}"#] {
+            fs::write(&policy_file_path, policy_json).unwrap();
+
+            assert!(service.get_namespace_policy(&namespace).unwrap().is_none());
+
+            assert!(service.store_default(&namespace, &name, &get_demo_entity()).is_ok());
+            assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+        }
+    }
+}
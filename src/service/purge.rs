@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error, info};
+
+use crate::codec::CodecKind;
+use crate::error::FileCacheError;
+use crate::file::get_file_size;
+use crate::janitor::{Janitor, PurgeReport};
+use crate::service::FileCacheService;
+use crate::service::scan::{CacheFileKind, parse_cache_filename};
+use crate::types::OperationResult;
+
+impl FileCacheService {
+    /// Remove expired items (after grace period) of all namespaces, corrupted items and orphaned files
+    /// (cache or metadata file without companion, temp files of interrupted writes).
+    /// Quarantined items are removed after `FileCacheServiceBuilder::quarantine_retention`,
+    /// item lock files of `get_or_insert_with` (see `FileCacheServiceBuilder::cross_process_single_flight`)
+    /// are removed if not in use.
+    ///
+    /// Corrupted items are handled according to `CorruptionPolicy`, kept with `CorruptionPolicy::Error`.
+    pub fn purge_expired(&self) -> OperationResult<PurgeReport> {
+        info!("purge expired items of file cache instance '{}'", self.instance_name);
+
+        let mut report = PurgeReport::default();
+
+        for namespace_dirname in self.list_namespace_dirnames()? {
+            let namespace_report = self.purge_namespace(&namespace_dirname)?;
+            report.append(&namespace_report);
+        }
+
+        if let Some(retention_secs) = self.quarantine_retention_secs {
+            report.append(&self.purge_quarantine(retention_secs)?);
+        }
+
+        let removed_item_locks = self.remove_all_unused_item_locks()?;
+        debug!("{} unused item lock files have been removed", removed_item_locks);
+
+        info!("file cache instance '{}' has been purged: {:?}", self.instance_name, report);
+        Ok(report)
+    }
+
+    /// Spawn background thread which calls `purge_expired` every `interval`.
+    ///
+    /// Thread is stopped with `Janitor::shutdown` or when `Janitor` is dropped.
+    pub fn spawn_janitor(&self, interval: Duration) -> OperationResult<Janitor> {
+        let service = self.clone();
+
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+
+        let handle = thread::Builder::new()
+            .name("fkesh-janitor".to_string())
+            .spawn(move || {
+                info!("file cache janitor has been started, interval {:?}", interval);
+
+                while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(interval) {
+                    if let Err(e) = service.purge_expired() {
+                        error!("file cache purge error: {}", e);
+                    }
+                }
+            })?;
+
+        Ok(Janitor::new(stop_sender, handle))
+    }
+
+    fn purge_namespace(&self, namespace_dirname: &str) -> OperationResult<PurgeReport> {
+        debug!("purge namespace '{}'", namespace_dirname);
+
+        let cache_item_path = self.get_cache_item_path(namespace_dirname);
+
+        let _lock = self.lock_namespace_exclusive(namespace_dirname)?;
+
+        let mut entry_names: Vec<String> = Vec::new();
+        let mut metadata_names: Vec<String> = Vec::new();
+        let mut cache_files: HashMap<String, Vec<CodecKind>> = HashMap::new();
+
+        let mut report = PurgeReport::default();
+
+        for entry in self.read_dir_if_exists(&cache_item_path)? {
+            let entry = entry?;
+            let filename = entry.file_name().to_string_lossy().to_string();
+
+            match parse_cache_filename(&filename) {
+                Some((name, CacheFileKind::Entry)) => entry_names.push(name),
+                Some((name, CacheFileKind::Metadata)) => metadata_names.push(name),
+                Some((name, CacheFileKind::Cache(codec))) => cache_files.entry(name).or_default().push(codec),
+                Some((_, CacheFileKind::Temp)) => {
+                    // Writers hold exclusive lock while temp file exists, so it's a leftover
+                    let size = get_file_size(&entry.path())?;
+
+                    if self.remove_file_if_exists(&entry.path())? {
+                        report.orphaned_files += 1;
+                        report.bytes_freed += size;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        for name in entry_names {
+            let entry_size = get_file_size(&self.get_entry_file_path(&cache_item_path, &name)?)?;
+
+            match self.read_entry_header(&cache_item_path, &name) {
+                Ok(None) => {}
+                Err(FileCacheError::Corrupted { reason, .. }) => {
+                    if self.discard_corrupted_entry(namespace_dirname, &name, &reason)? {
+                        report.corrupted_entries += 1;
+                        report.bytes_freed += entry_size;
+                    }
+                }
+                Err(e) => return Err(e),
+                Ok(Some(metadata)) if metadata.is_grace_period_over(now_unixtime) => {
+                    self.remove_cache_entry_files(&cache_item_path, &name)?;
+                    report.expired_entries += 1;
+                    report.bytes_freed += entry_size;
+                }
+                Ok(Some(_)) => {}
+            }
+
+            // Files of format v1, left by interrupted store: entry file takes precedence
+            metadata_names.retain(|metadata_name| *metadata_name != name);
+
+            let mut file_paths = vec![self.get_metadata_file_path(&cache_item_path, &name)?];
+
+            for codec in cache_files.remove(&name).unwrap_or_default() {
+                file_paths.push(self.get_codec_file_path(&cache_item_path, &name, codec)?);
+            }
+
+            for file_path in file_paths {
+                let size = get_file_size(&file_path)?;
+
+                if self.remove_file_if_exists(&file_path)? {
+                    report.orphaned_files += 1;
+                    report.bytes_freed += size;
+                }
+            }
+        }
+
+        for name in metadata_names {
+            let codecs = cache_files.remove(&name).unwrap_or_default();
+
+            let mut entry_size = get_file_size(&self.get_metadata_file_path(&cache_item_path, &name)?)?;
+
+            for codec in &codecs {
+                entry_size += get_file_size(&self.get_codec_file_path(&cache_item_path, &name, *codec)?)?;
+            }
+
+            match self.read_v1_metadata(&cache_item_path, &name) {
+                Ok(None) => continue,
+                Err(FileCacheError::Corrupted { reason, .. }) => {
+                    if self.discard_corrupted_entry(namespace_dirname, &name, &reason)? {
+                        report.corrupted_entries += 1;
+                        report.bytes_freed += entry_size;
+                    }
+                }
+                Err(e) => return Err(e),
+                Ok(Some(metadata)) if metadata.is_grace_period_over(now_unixtime) => {
+                    self.remove_cache_entry_files(&cache_item_path, &name)?;
+                    report.expired_entries += 1;
+                    report.bytes_freed += entry_size;
+                }
+                Ok(Some(metadata)) if !codecs.contains(&metadata.codec) => {
+                    // Metadata without its cache file
+                    self.remove_cache_entry_files(&cache_item_path, &name)?;
+                    report.orphaned_files += 1 + codecs.len();
+                    report.bytes_freed += entry_size;
+                }
+                Ok(Some(metadata)) => {
+                    // Cache files of other codecs, left by interrupted store
+                    for codec in codecs.iter().filter(|codec| **codec != metadata.codec) {
+                        let file_path = self.get_codec_file_path(&cache_item_path, &name, *codec)?;
+                        let size = get_file_size(&file_path)?;
+
+                        if self.remove_file_if_exists(&file_path)? {
+                            report.orphaned_files += 1;
+                            report.bytes_freed += size;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Cache files without metadata
+        for (name, codecs) in cache_files {
+            for codec in codecs {
+                let file_path = self.get_codec_file_path(&cache_item_path, &name, codec)?;
+                let size = get_file_size(&file_path)?;
+
+                if self.remove_file_if_exists(&file_path)? {
+                    report.orphaned_files += 1;
+                    report.bytes_freed += size;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod purge_expired_tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::expiry::Expiry;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{Demo, get_demo_entity, init_env_logging};
+
+    #[test]
+    fn expired_items_should_be_removed() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let other_namespace = get_random_nonblank_string();
+
+        let expired_name = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &expired_name, &get_demo_entity(), Duration::from_secs(1)).is_ok());
+        assert!(service.store(&other_namespace, &expired_name, &get_demo_entity(), Duration::from_secs(1)).is_ok());
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        clock.advance(Duration::from_secs(3));
+
+        let report = service.purge_expired().unwrap();
+
+        assert_eq!(report.expired_entries, 2);
+        assert_eq!(report.orphaned_files, 0);
+        assert!(report.bytes_freed > 0);
+
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+        let metadata_filename = format!("{}-{}", expired_name.as_ref(), METADATA_FILENAME_POSTFIX);
+        assert!(!namespace_path.join(metadata_filename).exists());
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+    }
+
+    #[test]
+    fn orphaned_companion_files_should_be_removed() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let first_name = get_random_nonblank_string();
+        let second_name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &first_name, &get_demo_entity(), Expiry::Never).is_ok());
+        assert!(service.store(&namespace, &second_name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+
+        let metadata_filename = format!("{}-{}", first_name.as_ref(), METADATA_FILENAME_POSTFIX);
+        fs::remove_file(namespace_path.join(metadata_filename)).unwrap();
+
+        let filename = format!("{}-{}", second_name.as_ref(), CACHE_FILENAME_POSTFIX);
+        fs::remove_file(namespace_path.join(filename)).unwrap();
+
+        fs::write(namespace_path.join(".tmp-demo"), "partial-data").unwrap();
+
+        let report = service.purge_expired().unwrap();
+
+        assert_eq!(report.expired_entries, 0);
+        assert_eq!(report.orphaned_files, 3);
+
+        assert_eq!(fs::read_dir(namespace_path).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn corrupted_items_should_be_removed() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+        fs::write(namespace_path.join(metadata_filename), "invalid-json-data").unwrap();
+
+        let report = service.purge_expired().unwrap();
+
+        assert_eq!(report.corrupted_entries, 1);
+        assert_eq!(fs::read_dir(namespace_path).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn return_empty_report_for_empty_instance() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        assert_eq!(service.purge_expired().unwrap().bytes_freed, 0);
+    }
+}
+
+#[cfg(test)]
+mod janitor_tests {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::service::{FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::tests::{get_demo_entity, init_env_logging};
+
+    #[test]
+    fn janitor_should_purge_expired_items_in_background() {
+        init_env_logging();
+
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name).clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(1)).is_ok());
+
+        clock.advance(Duration::from_secs(3));
+
+        let janitor = service.spawn_janitor(Duration::from_millis(100)).unwrap();
+
+        sleep(Duration::from_millis(500));
+
+        janitor.shutdown();
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        assert!(
+            !root_path.join(instance_name.as_ref())
+                .join(namespace.as_ref())
+                .join(metadata_filename)
+                .exists()
+        );
+    }
+
+    #[test]
+    fn janitor_should_be_stopped_on_drop() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(
+            &root_path_str, &instance_name).unwrap();
+
+        let janitor = service.spawn_janitor(Duration::from_secs(3600)).unwrap();
+
+        // Doesn't wait for interval to pass
+        drop(janitor);
+    }
+}
@@ -0,0 +1,411 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use log::{error, info, warn};
+
+use crate::codec::CodecKind;
+use crate::corruption::CorruptionPolicy;
+use crate::error::FileCacheError;
+use crate::file::get_file_size;
+use crate::janitor::PurgeReport;
+use crate::key::encode_key;
+use crate::service::FileCacheService;
+use crate::types::{EmptyResult, OperationResult};
+
+/// Directory for quarantined items, inside instance directory
+pub const QUARANTINE_DIRNAME: &str = ".quarantine";
+
+/// Quarantined item file with corruption details
+pub const QUARANTINE_REASON_FILENAME: &str = "reason.txt";
+
+/// Line of reason file with quarantine time, used for retention
+const QUARANTINED_UNIXTIME_PREFIX: &str = "quarantined_unixtime: ";
+
+impl FileCacheService {
+    /// Remove or quarantine files of corrupted item according to `CorruptionPolicy`.
+    /// Caller must hold exclusive namespace lock.
+    ///
+    /// Returns `true` if item files have been moved out of namespace directory.
+    pub(super) fn discard_corrupted_entry(&self, namespace_dirname: &str, name: &str,
+                                          reason: &str) -> OperationResult<bool> {
+        match self.corruption_policy {
+            CorruptionPolicy::Delete => {
+                error!("corrupted cache item '{}' will be removed", name);
+                let cache_item_path = self.get_cache_item_path(namespace_dirname);
+                self.remove_cache_entry_files(&cache_item_path, name)
+            }
+            CorruptionPolicy::Quarantine => self.quarantine_entry(namespace_dirname, name, reason),
+            CorruptionPolicy::Error => Ok(false)
+        }
+    }
+
+    fn quarantine_entry(&self, namespace_dirname: &str, name: &str, reason: &str) -> OperationResult<bool> {
+        let cache_item_path = self.get_cache_item_path(namespace_dirname);
+
+        let mut file_paths = vec![
+            self.get_entry_file_path(&cache_item_path, name)?,
+            self.get_metadata_file_path(&cache_item_path, name)?,
+        ];
+
+        for codec in CodecKind::all() {
+            file_paths.push(self.get_codec_file_path(&cache_item_path, name, *codec)?);
+        }
+
+        file_paths.retain(|file_path| file_path.exists());
+
+        if file_paths.is_empty() {
+            return Ok(false)
+        }
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+        let quarantine_path = self.create_quarantine_dir(namespace_dirname, name, now_unixtime)?;
+
+        for file_path in file_paths {
+            if let Some(filename) = file_path.file_name() {
+                match fs::rename(&file_path, quarantine_path.join(filename)) {
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => return Err(FileCacheError::from(e))
+                }
+            }
+        }
+
+        let reason_content = format!(
+            "namespace: {}\nitem: {}\n{}{}\nreason: {}\n",
+            namespace_dirname, name, QUARANTINED_UNIXTIME_PREFIX, now_unixtime, reason
+        );
+
+        fs::write(quarantine_path.join(QUARANTINE_REASON_FILENAME), reason_content)?;
+
+        warn!("corrupted cache item '{}' has been moved to quarantine '{}'", name, quarantine_path.display());
+        Ok(true)
+    }
+
+    /// Create `[INSTANCE NAME]/.quarantine/[NAMESPACE]/[ITEM NAME]-[UNIXTIME]` directory,
+    /// numeric suffix is added if it already exists. Caller must hold exclusive namespace lock.
+    ///
+    /// Namespace and item names are nested, so each component stays within file name limits.
+    fn create_quarantine_dir(&self, namespace_dirname: &str, name: &str,
+                             now_unixtime: u64) -> OperationResult<PathBuf> {
+        let quarantine_namespace_path = self.instance_path.join(QUARANTINE_DIRNAME).join(namespace_dirname);
+        fs::create_dir_all(&quarantine_namespace_path)?;
+
+        let dirname = format!("{}-{}", encode_key(name)?, now_unixtime);
+
+        let mut suffix: u32 = 0;
+
+        loop {
+            let quarantine_path = match suffix {
+                0 => quarantine_namespace_path.join(&dirname),
+                _ => quarantine_namespace_path.join(format!("{}-{}", dirname, suffix))
+            };
+
+            match fs::create_dir(&quarantine_path) {
+                Ok(_) => return Ok(quarantine_path),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => suffix += 1,
+                Err(e) => return Err(FileCacheError::from(e))
+            }
+        }
+    }
+
+    /// Remove quarantined items older than `retention_secs`, age is taken from `reason.txt`.
+    pub(super) fn purge_quarantine(&self, retention_secs: u64) -> OperationResult<PurgeReport> {
+        let quarantine_root_path = self.instance_path.join(QUARANTINE_DIRNAME);
+
+        let mut report = PurgeReport::default();
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        for entry in self.read_dir_if_exists(&quarantine_root_path)? {
+            let entry = entry?;
+
+            if !entry.file_type()?.is_dir() {
+                continue
+            }
+
+            let path = entry.path();
+
+            // Flat layout of previous versions: `[UNIXTIME]-[NAMESPACE]-[ITEM NAME]`
+            if path.join(QUARANTINE_REASON_FILENAME).exists() {
+                self.purge_quarantined_item(&path, retention_secs, now_unixtime, &mut report)?;
+                continue
+            }
+
+            let namespace_dirname = entry.file_name().to_string_lossy().to_string();
+
+            // Items are quarantined under namespace lock
+            let _lock = self.lock_namespace_exclusive(&namespace_dirname)?;
+
+            for item_entry in self.read_dir_if_exists(&path)? {
+                let item_entry = item_entry?;
+
+                if item_entry.file_type()?.is_dir() {
+                    self.purge_quarantined_item(&item_entry.path(), retention_secs, now_unixtime, &mut report)?;
+                }
+            }
+
+            if self.read_dir_if_exists(&path)?.next().is_none() {
+                self.remove_dir_if_exists(&path)?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn purge_quarantined_item(&self, quarantine_path: &Path, retention_secs: u64, now_unixtime: u64,
+                              report: &mut PurgeReport) -> EmptyResult {
+        let reason_content = match fs::read_to_string(quarantine_path.join(QUARANTINE_REASON_FILENAME)) {
+            Ok(reason_content) => reason_content,
+            // Item is being quarantined by another process, reason file is written last
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(FileCacheError::from(e))
+        };
+
+        let quarantined_unixtime = reason_content.lines()
+            .find_map(|line| line.strip_prefix(QUARANTINED_UNIXTIME_PREFIX))
+            .and_then(|value| value.trim().parse::<u64>().ok());
+
+        let Some(quarantined_unixtime) = quarantined_unixtime else {
+            warn!("quarantined item '{}' has invalid reason file, it's kept", quarantine_path.display());
+            return Ok(())
+        };
+
+        if now_unixtime.saturating_sub(quarantined_unixtime) < retention_secs {
+            return Ok(())
+        }
+
+        let mut size = 0;
+
+        for entry in self.read_dir_if_exists(quarantine_path)? {
+            size += get_file_size(&entry?.path())?;
+        }
+
+        if self.remove_dir_if_exists(quarantine_path)? {
+            info!("quarantined item '{}' has been removed after retention", quarantine_path.display());
+            report.quarantined_entries += 1;
+            report.bytes_freed += size;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod corruption_policy_tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::corruption::CorruptionPolicy;
+    use crate::error::FileCacheError;
+    use crate::expiry::Expiry;
+    use crate::key::MAX_ENCODED_KEY_LENGTH;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService, METADATA_FILENAME_POSTFIX};
+    use crate::service::quarantine::{QUARANTINE_DIRNAME, QUARANTINE_REASON_FILENAME};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn corrupted_item_should_be_moved_to_quarantine() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .corruption_policy(CorruptionPolicy::Quarantine).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        fs::write(namespace_path.join(&filename), "invalid-json-data").unwrap();
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+
+        assert!(!namespace_path.join(&filename).exists());
+        assert!(!namespace_path.join(&metadata_filename).exists());
+
+        let quarantine_path = root_path.join(instance_name.as_ref()).join(QUARANTINE_DIRNAME).join(namespace.as_ref());
+
+        let quarantined_dirs: Vec<_> = fs::read_dir(&quarantine_path).unwrap()
+                                            .map(|entry| entry.unwrap().path()).collect();
+
+        assert_eq!(quarantined_dirs.len(), 1);
+
+        let quarantined_path = &quarantined_dirs[0];
+
+        assert_eq!(fs::read_to_string(quarantined_path.join(&filename)).unwrap(), "invalid-json-data");
+        assert!(quarantined_path.join(&metadata_filename).exists());
+
+        let reason = fs::read_to_string(quarantined_path.join(QUARANTINE_REASON_FILENAME)).unwrap();
+        assert!(reason.contains(name.as_ref()));
+    }
+
+    #[test]
+    fn quarantine_should_be_kept_on_instance_invalidation() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .corruption_policy(CorruptionPolicy::Quarantine).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        fs::write(
+            root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(metadata_filename),
+            "invalid-json-data"
+        ).unwrap();
+
+        let report = service.purge_expired().unwrap();
+        assert_eq!(report.corrupted_entries, 1);
+
+        assert!(service.invalidate_instance().is_ok());
+
+        let quarantine_path = root_path.join(instance_name.as_ref()).join(QUARANTINE_DIRNAME);
+        assert_eq!(fs::read_dir(quarantine_path).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn item_with_long_names_should_be_quarantined() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .corruption_policy(CorruptionPolicy::Quarantine).build().unwrap();
+
+        let namespace = NonBlankString::parse(&"n".repeat(MAX_ENCODED_KEY_LENGTH)).unwrap();
+        let name = NonBlankString::parse(&"i".repeat(MAX_ENCODED_KEY_LENGTH)).unwrap();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let namespace_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref());
+
+        fs::write(namespace_path.join(&filename), "invalid-json-data").unwrap();
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+
+        let quarantine_path = root_path.join(instance_name.as_ref()).join(QUARANTINE_DIRNAME).join(namespace.as_ref());
+        assert_eq!(fs::read_dir(quarantine_path).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn quarantined_items_should_be_removed_after_retention() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .corruption_policy(CorruptionPolicy::Quarantine)
+            .quarantine_retention(Duration::from_secs(3600))
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        fs::write(
+            root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(metadata_filename),
+            "invalid-json-data"
+        ).unwrap();
+
+        let report = service.purge_expired().unwrap();
+        assert_eq!(report.corrupted_entries, 1);
+        assert_eq!(report.quarantined_entries, 0);
+
+        let quarantine_path = root_path.join(instance_name.as_ref()).join(QUARANTINE_DIRNAME);
+        assert_eq!(fs::read_dir(&quarantine_path).unwrap().count(), 1);
+
+        clock.advance(Duration::from_secs(3600));
+
+        let report = service.purge_expired().unwrap();
+        assert_eq!(report.quarantined_entries, 1);
+        assert!(report.bytes_freed > 0);
+
+        assert_eq!(fs::read_dir(&quarantine_path).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn error_policy_should_return_error_and_keep_files() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::builder(
+            &root_path_str, &instance_name)
+            .corruption_policy(CorruptionPolicy::Error).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Expiry::Never).is_ok());
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+
+        let cache_item_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref()).join(filename);
+
+        fs::write(&cache_item_path, "invalid-json-data").unwrap();
+
+        match service.get::<Demo>(&namespace, &name) {
+            Err(FileCacheError::Corrupted { path, .. }) => assert_eq!(path, cache_item_path),
+            _ => panic!("corrupted error expected")
+        }
+
+        assert!(matches!(service.get::<u64>(&namespace, &name), Err(FileCacheError::Corrupted { .. })));
+
+        assert!(cache_item_path.exists());
+
+        let metadata_filename = format!("{}-{}", name.as_ref(), METADATA_FILENAME_POSTFIX);
+
+        let metadata_item_path = Path::new(root_path_str.as_ref())
+            .join(instance_name.as_ref())
+            .join(namespace.as_ref()).join(metadata_filename);
+
+        fs::write(&metadata_item_path, "invalid-json-data").unwrap();
+
+        let report = service.purge_expired().unwrap();
+        assert_eq!(report.corrupted_entries, 0);
+
+        assert!(metadata_item_path.exists());
+        assert!(cache_item_path.exists());
+    }
+}
@@ -0,0 +1,172 @@
+use std::fs;
+use std::fs::DirEntry;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::codec::CodecKind;
+use crate::error::FileCacheError;
+use crate::file::TEMP_FILENAME_PREFIX;
+use crate::format::ENTRY_FILENAME_POSTFIX;
+use crate::key::decode_key;
+use crate::listing::ListOptions;
+use crate::service::{CACHE_FILENAME_STEM, FileCacheService, METADATA_FILENAME_POSTFIX};
+use crate::types::OperationResult;
+
+/// Kind of file in namespace directory
+#[derive(PartialEq, Debug)]
+pub(super) enum CacheFileKind {
+    Metadata,
+
+    Cache(CodecKind),
+
+    /// Entry file of format v2
+    Entry,
+
+    /// Leftover of interrupted write
+    Temp,
+}
+
+/// Parse file name in namespace directory, returns decoded item name (empty for temp files).
+///
+/// Returns `None` for unknown files.
+pub(super) fn parse_cache_filename(filename: &str) -> Option<(String, CacheFileKind)> {
+    if filename.starts_with(TEMP_FILENAME_PREFIX) {
+        return Some((String::new(), CacheFileKind::Temp))
+    }
+
+    let metadata_postfix = format!("-{}", METADATA_FILENAME_POSTFIX);
+
+    if let Some(encoded_name) = filename.strip_suffix(&metadata_postfix) {
+        return decode_key(encoded_name).map(|name| (name, CacheFileKind::Metadata))
+    }
+
+    let entry_postfix = format!("-{}", ENTRY_FILENAME_POSTFIX);
+
+    if let Some(encoded_name) = filename.strip_suffix(&entry_postfix) {
+        return decode_key(encoded_name).map(|name| (name, CacheFileKind::Entry))
+    }
+
+    for codec in CodecKind::all() {
+        let postfix = format!("-{}.{}", CACHE_FILENAME_STEM, codec.extension());
+
+        if let Some(encoded_name) = filename.strip_suffix(&postfix) {
+            return decode_key(encoded_name).map(|name| (name, CacheFileKind::Cache(*codec)))
+        }
+    }
+
+    None
+}
+
+impl FileCacheService {
+    /// Directory names (encoded) of instance namespaces.
+    ///
+    /// Service directories (`.locks` etc.) are skipped.
+    pub(super) fn list_namespace_dirnames(&self) -> OperationResult<Vec<String>> {
+        let mut dirnames = Vec::new();
+
+        for entry in self.read_dir_if_exists(&self.instance_path)? {
+            let entry = entry?;
+            let dirname = entry.file_name().to_string_lossy().to_string();
+
+            if !dirname.starts_with('.') && entry.file_type()?.is_dir() {
+                dirnames.push(dirname);
+            }
+        }
+
+        Ok(dirnames)
+    }
+
+    /// Names (decoded) of items with metadata file or entry file in namespace directory.
+    pub(super) fn list_item_names(&self, cache_item_path: &Path) -> OperationResult<Vec<String>> {
+        let mut names = Vec::new();
+
+        for entry in self.read_dir_if_exists(cache_item_path)? {
+            let filename = entry?.file_name().to_string_lossy().to_string();
+
+            if let Some((name, CacheFileKind::Metadata | CacheFileKind::Entry)) = parse_cache_filename(&filename) {
+                names.push(name);
+            }
+        }
+
+        // Both files exist if store of another format has been interrupted
+        names.sort();
+        names.dedup();
+
+        Ok(names)
+    }
+
+    /// Names (decoded) of items in namespace, sorted.
+    ///
+    /// Expired items are skipped unless `options.include_expired`,
+    /// corrupted items and items without cache file are always skipped.
+    pub(super) fn list_item_names_with(&self, namespace_dirname: &str,
+                                       options: &ListOptions) -> OperationResult<Vec<String>> {
+        let cache_item_path = self.get_cache_item_path(namespace_dirname);
+
+        if !cache_item_path.exists() {
+            return Ok(Vec::new())
+        }
+
+        let _lock = self.lock_namespace_shared(namespace_dirname)?;
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        let mut names = Vec::new();
+
+        for name in self.list_item_names(&cache_item_path)? {
+            match self.read_metadata(&cache_item_path, &name) {
+                Ok(Some(metadata)) => {
+                    let listed = options.include_expired || !metadata.is_expired(now_unixtime);
+
+                    if listed && self.get_payload_size(&cache_item_path, &name, metadata.codec)?.is_some() {
+                        names.push(name);
+                    }
+                }
+                Ok(None) | Err(FileCacheError::Corrupted { .. }) => {}
+                Err(e) => return Err(e)
+            }
+        }
+
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Directory entries, empty if directory doesn't exist
+    pub(super) fn read_dir_if_exists(&self, dir_path: &Path) -> OperationResult<impl Iterator<Item=std::io::Result<DirEntry>>> {
+        match fs::read_dir(dir_path) {
+            Ok(entries) => Ok(Some(entries).into_iter().flatten()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None.into_iter().flatten()),
+            Err(e) => Err(FileCacheError::from(e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod parse_cache_filename_tests {
+    use crate::codec::CodecKind;
+    use crate::service::scan::{CacheFileKind, parse_cache_filename};
+
+    #[test]
+    fn metadata_filename_should_be_parsed() {
+        assert_eq!(parse_cache_filename("users%2F1-cache-metadata.json"),
+                   Some(("users/1".to_string(), CacheFileKind::Metadata)));
+    }
+
+    #[test]
+    fn cache_filename_should_be_parsed() {
+        assert_eq!(parse_cache_filename("demo-cache-cache.json"),
+                   Some(("demo-cache".to_string(), CacheFileKind::Cache(CodecKind::Json))));
+    }
+
+    #[test]
+    fn entry_filename_should_be_parsed() {
+        assert_eq!(parse_cache_filename("demo-entry.fkesh"),
+                   Some(("demo".to_string(), CacheFileKind::Entry)));
+    }
+
+    #[test]
+    fn return_none_for_unknown_file() {
+        assert!(parse_cache_filename("readme.txt").is_none());
+    }
+}
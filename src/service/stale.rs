@@ -0,0 +1,167 @@
+use log::{error, info};
+use non_blank_string_rs::NonBlankString;
+use serde::de::DeserializeOwned;
+
+use crate::error::FileCacheError;
+use crate::freshness::Freshness;
+use crate::key::encode_key;
+use crate::service::{CacheEntryState, FileCacheService};
+use crate::types::OptionalResult;
+
+impl FileCacheService {
+    /// Get item like `get`, but expired item is returned as `Freshness::Stale` during its grace period
+    /// (see `FileCacheServiceBuilder::grace_period`), e.g. to serve it while upstream is unavailable.
+    ///
+    /// Stale items aren't removed, they are removed by `get` and `purge_expired` after grace period.
+    pub fn get_allow_stale<T: DeserializeOwned>(&self, namespace: &NonBlankString,
+                                                name: &NonBlankString) -> OptionalResult<(T, Freshness)> {
+        if let Some(value) = self.get_item::<T>(namespace.as_ref(), name.as_ref())? {
+            return Ok(Some((value, Freshness::Fresh)))
+        }
+
+        Ok(self.read_stale_item::<T>(namespace.as_ref(), name.as_ref())?.map(|value| (value, Freshness::Stale)))
+    }
+
+    /// Read expired item in grace period, `None` for fresh, missing and corrupted items
+    pub(super) fn read_stale_item<T: DeserializeOwned>(&self, namespace: &str, name: &str) -> OptionalResult<T> {
+        let namespace_dirname = encode_key(namespace)?;
+        let cache_item_path = self.get_cache_item_path(&namespace_dirname);
+
+        if !cache_item_path.exists() {
+            return Ok(None)
+        }
+
+        let _lock = self.lock_namespace_shared(&namespace_dirname)?;
+
+        let metadata = match self.read_metadata(&cache_item_path, name) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => return Ok(None),
+            Err(FileCacheError::Corrupted { path, reason }) => {
+                error!("corrupted metadata file '{}': {}", path.display(), reason);
+                return Ok(None)
+            }
+            Err(e) => return Err(e)
+        };
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        if !metadata.is_expired(now_unixtime) || metadata.is_grace_period_over(now_unixtime) {
+            return Ok(None)
+        }
+
+        match self.read_cache_file::<T>(&cache_item_path, name, metadata)? {
+            CacheEntryState::Found { value, .. } => {
+                info!("stale entity '{}' has been loaded from file cache", name);
+                Ok(Some(value))
+            }
+            _ => Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod stale_tests {
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::ManualClock;
+    use crate::freshness::Freshness;
+    use crate::policy::NamespacePolicy;
+    use crate::service::FileCacheService;
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn expired_item_should_be_returned_as_stale_in_grace_period() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .grace_period(Duration::from_secs(300))
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let demo = get_demo_entity();
+
+        assert!(service.store(&namespace, &name, &demo, Duration::from_secs(60)).is_ok());
+
+        let (value, freshness) = service.get_allow_stale::<Demo>(&namespace, &name).unwrap().unwrap();
+        assert_eq!(value, demo);
+        assert_eq!(freshness, Freshness::Fresh);
+
+        clock.advance(Duration::from_secs(120));
+
+        // `get` and `purge_expired` keep item during grace period
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+        assert_eq!(service.purge_expired().unwrap().expired_entries, 0);
+
+        let (value, freshness) = service.get_allow_stale::<Demo>(&namespace, &name).unwrap().unwrap();
+        assert_eq!(value, demo);
+        assert_eq!(freshness, Freshness::Stale);
+
+        clock.advance(Duration::from_secs(300));
+
+        assert_eq!(service.purge_expired().unwrap().expired_entries, 1);
+        assert!(service.get_allow_stale::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn expired_item_should_be_removed_without_grace_period() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+
+        clock.advance(Duration::from_secs(61));
+
+        assert!(service.get_allow_stale::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn namespace_policy_grace_period_should_be_used() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let policy = NamespacePolicy { grace_period_secs: Some(300), ..NamespacePolicy::default() };
+        assert!(service.set_namespace_policy(&namespace, &policy).is_ok());
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+
+        clock.advance(Duration::from_secs(120));
+
+        let (_, freshness) = service.get_allow_stale::<Demo>(&namespace, &name).unwrap().unwrap();
+        assert_eq!(freshness, Freshness::Stale);
+    }
+}
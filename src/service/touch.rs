@@ -0,0 +1,380 @@
+use std::path::{Path, PathBuf};
+
+use log::{debug, error, info};
+use non_blank_string_rs::NonBlankString;
+
+use crate::error::FileCacheError;
+use crate::expiry::Expiry;
+use crate::key::encode_key;
+use crate::memory::FileStamp;
+use crate::service::{FileCacheItemMetadata, FileCacheService, get_legacy_ttl_secs};
+use crate::types::{OperationResult, OptionalResult};
+
+/// Metadata updated on access, to be written after shared namespace lock is released
+pub(super) struct AccessUpdate {
+    pub(super) metadata: FileCacheItemMetadata,
+
+    /// File with metadata (entry file or metadata file) and its stamp when update was made
+    pub(super) metadata_file_path: PathBuf,
+    pub(super) stamp: FileStamp,
+}
+
+impl FileCacheService {
+    /// Extend life of item with cache `name` in `namespace`. Only metadata file is rewritten (atomically),
+    /// cache file is left as is.
+    ///
+    /// - `expiry` - new expiry counted from now, `None` - TTL set on store counted from now
+    ///   (items stored with `Expiry::At` keep their expiration)
+    ///
+    /// Returns `false` if item is missing, expired or corrupted.
+    pub fn touch(&self, namespace: &NonBlankString, name: &NonBlankString,
+                 expiry: Option<Expiry>) -> OperationResult<bool> {
+        info!("touch entity in file cache: namespace='{}', item_name='{}'", namespace.as_ref(), name.as_ref());
+
+        let namespace_dirname = encode_key(namespace.as_ref())?;
+        let cache_item_path = self.get_cache_item_path(&namespace_dirname);
+
+        if !cache_item_path.exists() {
+            info!("file cache entity '{}' wasn't found", name.as_ref());
+            return Ok(false)
+        }
+
+        let _lock = self.lock_namespace_exclusive(&namespace_dirname)?;
+
+        let mut metadata = match self.read_metadata(&cache_item_path, name.as_ref()) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => return Ok(false),
+            Err(FileCacheError::Corrupted { path, reason }) => {
+                error!("corrupted metadata file '{}': {}", path.display(), reason);
+                return Ok(false)
+            }
+            Err(e) => return Err(e)
+        };
+
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        if metadata.is_expired(now_unixtime) ||
+            self.get_payload_size(&cache_item_path, name.as_ref(), metadata.codec)?.is_none() {
+            info!("file cache entity '{}' wasn't found or has been expired", name.as_ref());
+            return Ok(false)
+        }
+
+        match expiry {
+            Some(expiry) => {
+                metadata.expires_at = expiry.get_expires_at_unixtime(now_unixtime);
+                metadata.ttl_secs = get_legacy_ttl_secs(metadata.expires_at, now_unixtime);
+                metadata.relative_expiry = matches!(expiry, Expiry::After(_));
+            }
+            None => {
+                metadata.expires_at = metadata.get_sliding_expires_at_unixtime(now_unixtime);
+                metadata.ttl_secs = get_legacy_ttl_secs(metadata.expires_at, now_unixtime);
+            }
+        }
+
+        self.write_metadata(&cache_item_path, name.as_ref(), &metadata)?;
+
+        info!("item '{}' has been touched", name.as_ref());
+        Ok(true)
+    }
+
+    /// Metadata update on successful `get`: last access time (tracked if cache limits are set)
+    /// and expiration (sliding expiration). Caller must hold namespace lock, shared one is enough.
+    ///
+    /// Metadata is rewritten at most once per second, `None` if nothing has changed.
+    pub(super) fn get_access_update(&self, namespace_dirname: &str, cache_item_path: &Path, name: &str,
+                                    metadata: &FileCacheItemMetadata) -> OptionalResult<AccessUpdate> {
+        let now_unixtime = self.get_now_in_unixtime_secs()?;
+
+        let access_tracked = metadata.get_last_access_unixtime() < now_unixtime &&
+            self.is_access_tracked(namespace_dirname)?;
+
+        let Some(metadata) = self.get_accessed_metadata(metadata, now_unixtime, access_tracked) else {
+            return Ok(None)
+        };
+
+        Ok(self.read_metadata_stamp(cache_item_path, name)?
+            .map(|(metadata_file_path, stamp)| AccessUpdate { metadata, metadata_file_path, stamp }))
+    }
+
+    /// Rewrite metadata with `update`, must be called without namespace lock.
+    ///
+    /// Readers hold shared lock, so update is written only if exclusive lock is available right away
+    /// and item hasn't been rewritten since `update` was made. Otherwise update is skipped:
+    /// last access time and sliding expiration are best effort.
+    ///
+    /// Returns up-to-date metadata: updated one or `metadata` if update is skipped.
+    pub(super) fn update_on_access(&self, namespace_dirname: &str, cache_item_path: &Path, name: &str,
+                                   metadata: FileCacheItemMetadata,
+                                   update: AccessUpdate) -> OperationResult<FileCacheItemMetadata> {
+        let Some(_lock) = self.try_lock_namespace_exclusive(namespace_dirname)? else {
+            debug!("namespace is locked, metadata update on access is skipped for item '{}'", name);
+            return Ok(metadata)
+        };
+
+        if FileStamp::read(&update.metadata_file_path)? != Some(update.stamp) {
+            debug!("item '{}' has been rewritten, metadata update on access is skipped", name);
+            return Ok(metadata)
+        }
+
+        self.write_metadata(cache_item_path, name, &update.metadata)?;
+        debug!("metadata has been updated on access for item '{}'", name);
+
+        Ok(update.metadata)
+    }
+
+    /// Metadata updated on access at `now_unixtime`, `None` if nothing has changed
+    pub(super) fn get_accessed_metadata(&self, metadata: &FileCacheItemMetadata, now_unixtime: u64,
+                                        access_tracked: bool) -> Option<FileCacheItemMetadata> {
+        let mut metadata = metadata.clone();
+        let mut updated = false;
+
+        if access_tracked && metadata.get_last_access_unixtime() < now_unixtime {
+            metadata.last_access_unixtime = Some(now_unixtime);
+            updated = true;
+        }
+
+        if self.sliding_expiration {
+            let expires_at = metadata.get_sliding_expires_at_unixtime(now_unixtime);
+
+            if expires_at > metadata.get_expires_at_unixtime() {
+                metadata.expires_at = expires_at;
+                updated = true;
+            }
+        }
+
+        updated.then_some(metadata)
+    }
+}
+
+#[cfg(test)]
+mod touch_tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use non_blank_string_rs::NonBlankString;
+    use non_blank_string_rs::utils::get_random_nonblank_string;
+    use tempfile::tempdir;
+
+    use crate::clock::{Clock, ManualClock};
+    use crate::expiry::Expiry;
+    use crate::key::encode_key;
+    use crate::service::{CACHE_FILENAME_POSTFIX, FileCacheService};
+    use crate::tests::{Demo, get_demo_entity};
+
+    #[test]
+    fn touch_should_extend_item_life() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+
+        let filename = format!("{}-{}", name.as_ref(), CACHE_FILENAME_POSTFIX);
+        let file_path = root_path.join(instance_name.as_ref()).join(namespace.as_ref()).join(filename);
+        let data = fs::read(&file_path).unwrap();
+
+        // TTL set on store is counted from now
+        clock.advance(Duration::from_secs(50));
+        assert!(service.touch(&namespace, &name, None).unwrap());
+
+        clock.advance(Duration::from_secs(50));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        assert!(service.touch(&namespace, &name, Some(Expiry::After(Duration::from_secs(300)))).unwrap());
+
+        clock.advance(Duration::from_secs(200));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        assert_eq!(fs::read(&file_path).unwrap(), data);
+
+        clock.advance(Duration::from_secs(101));
+        assert!(!service.touch(&namespace, &name, None).unwrap());
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn touch_should_return_false_for_missing_item() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let service = FileCacheService::new(&root_path_str, &instance_name).unwrap();
+
+        let namespace = get_random_nonblank_string();
+
+        assert!(!service.touch(&namespace, &get_random_nonblank_string(), None).unwrap());
+
+        assert!(service.store(&namespace, &get_random_nonblank_string(), &get_demo_entity(), Expiry::Never).is_ok());
+
+        assert!(!service.touch(&namespace, &get_random_nonblank_string(), Some(Expiry::Never)).unwrap());
+    }
+
+    #[test]
+    fn get_should_push_expiration_with_sliding_expiration() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .sliding_expiration(true)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let session = get_random_nonblank_string();
+        let immortal = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &session, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+        assert!(service.store(&namespace, &immortal, &get_demo_entity(), Expiry::Never).is_ok());
+
+        for _ in 0..3 {
+            clock.advance(Duration::from_secs(40));
+            assert!(service.get::<Demo>(&namespace, &session).unwrap().is_some());
+        }
+
+        assert!(service.get::<Demo>(&namespace, &immortal).unwrap().is_some());
+        assert!(service.metadata(&namespace, &immortal).unwrap().unwrap().expires_at.is_none());
+
+        clock.advance(Duration::from_secs(61));
+        assert!(service.get::<Demo>(&namespace, &session).unwrap().is_none());
+    }
+
+    #[test]
+    fn sliding_expiration_should_not_push_absolute_expiration() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .sliding_expiration(true)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let expires_at = clock.now() + Duration::from_secs(60);
+        assert!(service.store(&namespace, &name, &get_demo_entity(), expires_at).is_ok());
+
+        clock.advance(Duration::from_secs(40));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+        assert!(service.touch(&namespace, &name, None).unwrap());
+
+        clock.advance(Duration::from_secs(40));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+
+    #[test]
+    fn touch_should_keep_ttl_consistent_with_expiration() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        let expires_at = clock.now() + Duration::from_secs(1000);
+        assert!(service.store(&namespace, &name, &get_demo_entity(), expires_at).is_ok());
+
+        let cache_item_path = service.get_cache_item_path(&encode_key(namespace.as_ref()).unwrap());
+        let stored_metadata = service.read_metadata(&cache_item_path, name.as_ref()).unwrap().unwrap();
+
+        clock.advance(Duration::from_secs(400));
+        assert!(service.touch(&namespace, &name, None).unwrap());
+
+        let metadata = service.read_metadata(&cache_item_path, name.as_ref()).unwrap().unwrap();
+
+        assert_eq!(metadata.expires_at, stored_metadata.expires_at);
+        assert_eq!(metadata.ttl_secs, 600);
+    }
+
+    #[test]
+    fn access_update_should_be_skipped_while_namespace_is_locked() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+            .sliding_expiration(true)
+            .clock(clock.clone())
+            .build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+
+        let expires_at = service.metadata(&namespace, &name).unwrap().unwrap().expires_at;
+
+        clock.advance(Duration::from_secs(40));
+
+        {
+            // Another reader
+            let namespace_dirname = encode_key(namespace.as_ref()).unwrap();
+            let _lock = service.lock_namespace_shared(&namespace_dirname).unwrap();
+
+            assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+            assert_eq!(service.metadata(&namespace, &name).unwrap().unwrap().expires_at, expires_at);
+        }
+
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+        assert!(service.metadata(&namespace, &name).unwrap().unwrap().expires_at > expires_at);
+    }
+
+    #[test]
+    fn get_should_not_push_expiration_by_default() {
+        let root_path_tmp = tempdir().unwrap();
+        let root_path = root_path_tmp.path();
+        let root_path_str = NonBlankString::parse(&format!("{}", root_path.display())).unwrap();
+
+        let instance_name = get_random_nonblank_string();
+
+        let clock = ManualClock::default();
+
+        let service = FileCacheService::builder(&root_path_str, &instance_name)
+                                            .clock(clock.clone()).build().unwrap();
+
+        let namespace = get_random_nonblank_string();
+        let name = get_random_nonblank_string();
+
+        assert!(service.store(&namespace, &name, &get_demo_entity(), Duration::from_secs(60)).is_ok());
+
+        clock.advance(Duration::from_secs(40));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_some());
+
+        clock.advance(Duration::from_secs(40));
+        assert!(service.get::<Demo>(&namespace, &name).unwrap().is_none());
+    }
+}
@@ -2,7 +2,7 @@ use fake::{Fake, Faker};
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Demo {
     pub login: String,
 }